@@ -0,0 +1,33 @@
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// Decoder crates whose resolved versions are recorded with every decoded row, as <ENV_VAR>=<crate>@<version>
+const DECODER_CRATES: &[(&str, &str)] = &[
+    ("METEORA_DAMM_V2_DECODER_VERSION", "carbon-meteora-damm-v2-decoder"),
+    ("DRIFT_V2_DECODER_VERSION", "carbon-drift-v2-decoder"),
+];
+
+fn main() {
+    let lockfile = Path::new(&env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.lock");
+    println!("cargo:rerun-if-changed={}", lockfile.display());
+    println!("cargo:rerun-if-changed=build.rs");
+
+    // Cargo resolves the lockfile before running build scripts
+    let lock = fs::read_to_string(&lockfile).expect("Cargo.lock is missing");
+
+    for (env_var, crate_name) in DECODER_CRATES {
+        let version = locked_version(&lock, crate_name)
+            .unwrap_or_else(|| panic!("{} is not in Cargo.lock", crate_name));
+        println!("cargo:rustc-env={}={}@{}", env_var, crate_name, version);
+    }
+}
+
+// Version of the crate's [[package]] entry; the first one if several versions are locked
+fn locked_version<'a>(lock: &'a str, crate_name: &str) -> Option<&'a str> {
+    let name_line = format!("name = \"{}\"", crate_name);
+    let mut lines = lock.lines().skip_while(|line| line.trim() != name_line).skip(1);
+    lines
+        .find_map(|line| line.trim().strip_prefix("version = \""))
+        .and_then(|rest| rest.strip_suffix('"'))
+}
@@ -0,0 +1,4 @@
+-- Tag every account update with the program that owns the decoder which produced it
+ALTER TABLE account_updates ADD COLUMN program_id TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX idx_program_id ON account_updates(program_id);
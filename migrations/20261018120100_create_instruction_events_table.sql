@@ -0,0 +1,18 @@
+-- Create instruction_events table for decoded program instructions (swaps, liquidity changes, ...)
+CREATE TABLE instruction_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    signature TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    instruction_index INTEGER NOT NULL,
+    program_id TEXT NOT NULL,
+    instruction_type TEXT NOT NULL,
+    pool TEXT,
+    accounts_json TEXT NOT NULL,
+    data_json TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Indexes for efficient querying
+CREATE INDEX idx_instruction_signature ON instruction_events(signature);
+CREATE INDEX idx_instruction_pool_slot ON instruction_events(pool, slot DESC);
+CREATE INDEX idx_instruction_type ON instruction_events(instruction_type);
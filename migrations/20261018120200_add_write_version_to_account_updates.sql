@@ -0,0 +1,3 @@
+-- Geyser write version and the transaction that last wrote the account, when the datasource reports them
+ALTER TABLE account_updates ADD COLUMN write_version INTEGER;
+ALTER TABLE account_updates ADD COLUMN transaction_signature TEXT;
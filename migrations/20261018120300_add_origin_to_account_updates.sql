@@ -0,0 +1,2 @@
+-- Where an update came from: 'live' stream or 'backfill' snapshot
+ALTER TABLE account_updates ADD COLUMN origin TEXT NOT NULL DEFAULT 'live';
@@ -0,0 +1,4 @@
+-- Commitment level an update was stored at, upgraded in place when commitment tracking is enabled.
+-- Rows stored before this were ingested at finalized, so they're never upgraded again.
+ALTER TABLE account_updates ADD COLUMN commitment TEXT NOT NULL DEFAULT 'finalized';
+CREATE INDEX idx_account_updates_commitment_slot ON account_updates(commitment, slot);
@@ -0,0 +1,2 @@
+-- Updates from slots that never made it onto the confirmed chain
+ALTER TABLE account_updates ADD COLUMN orphaned BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+-- Hash of the raw account (lamports, owner, data) to skip redelivered unchanged accounts,
+-- and the last slot an unchanged delivery was seen at when heartbeats are enabled
+ALTER TABLE account_updates ADD COLUMN data_hash TEXT;
+ALTER TABLE account_updates ADD COLUMN last_seen_slot INTEGER;
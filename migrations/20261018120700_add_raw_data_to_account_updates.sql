@@ -0,0 +1,5 @@
+-- Raw account bytes, so rows can be re-decoded or handed to clients' own decoders.
+-- rent_epoch holds the u64 bit pattern (u64::MAX, rent exempt, reads back as -1).
+ALTER TABLE account_updates ADD COLUMN data BLOB;
+ALTER TABLE account_updates ADD COLUMN executable BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE account_updates ADD COLUMN rent_epoch INTEGER;
@@ -0,0 +1,17 @@
+-- Decoder that produced data_json, so rows can be re-decoded after decoder upgrades
+ALTER TABLE account_updates ADD COLUMN decoder_version TEXT;
+
+-- Re-decodings kept next to the original JSON, one per decoder version
+CREATE TABLE account_update_versions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    account_update_id INTEGER NOT NULL REFERENCES account_updates(id),
+    decoder_version TEXT NOT NULL,
+    account_type TEXT NOT NULL,
+    data_json TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (account_update_id, decoder_version)
+);
+
+-- Walks a program's rows in id order for re-decoding; its program_id prefix replaces idx_program_id
+CREATE INDEX idx_account_updates_program_id ON account_updates(program_id, id);
+DROP INDEX idx_program_id;
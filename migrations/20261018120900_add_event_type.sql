@@ -0,0 +1,2 @@
+-- What happened to the account: 'updated', or a tombstone for 'closed' and 'owner_changed'
+ALTER TABLE account_updates ADD COLUMN event_type TEXT NOT NULL DEFAULT 'updated';
@@ -0,0 +1,3 @@
+-- Spot prices derived from a pool's sqrt_price, adjusted for token decimals; NULL for other accounts
+ALTER TABLE account_updates ADD COLUMN price_a_to_b REAL;
+ALTER TABLE account_updates ADD COLUMN price_b_to_a REAL;
@@ -0,0 +1,11 @@
+-- Metadata of the mints pools reference, refreshed once it is older than an hour
+CREATE TABLE mint_metadata (
+    mint TEXT PRIMARY KEY,
+    decimals INTEGER NOT NULL,
+    -- u64, which INTEGER can't hold in full
+    supply TEXT NOT NULL,
+    token_program TEXT NOT NULL,
+    name TEXT,
+    symbol TEXT,
+    updated_at DATETIME NOT NULL
+);
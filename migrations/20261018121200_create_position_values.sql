@@ -0,0 +1,15 @@
+-- Latest valuation of each Meteora DAMM V2 position; values are NULL until its pool is stored.
+-- u64 amounts are stored as their i64 bit pattern, like rent_epoch.
+CREATE TABLE position_values (
+    position TEXT PRIMARY KEY,
+    pool TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    amount_a INTEGER,
+    amount_b INTEGER,
+    fee_a INTEGER,
+    fee_b INTEGER,
+    reward_0 INTEGER,
+    reward_1 INTEGER,
+    updated_at DATETIME NOT NULL
+);
+CREATE INDEX idx_position_values_pool ON position_values(pool);
@@ -0,0 +1,12 @@
+-- Pool each open Meteora DAMM V2 position belongs to; mirrored in Redis as pool_positions:<pool> sets.
+CREATE TABLE pool_positions (
+    position TEXT PRIMARY KEY,
+    pool TEXT NOT NULL,
+    slot INTEGER NOT NULL,
+    created_at DATETIME NOT NULL
+);
+CREATE INDEX idx_pool_positions_pool ON pool_positions(pool);
+
+-- Positions valued so far were already linked to their pool
+INSERT INTO pool_positions (position, pool, slot, created_at)
+SELECT position, pool, slot, updated_at FROM position_values;
@@ -0,0 +1,12 @@
+-- NFT mint of each open Meteora DAMM V2 position, and the token account and wallet currently holding it.
+-- token_account and owner are NULL until the holder has been resolved.
+CREATE TABLE position_nfts (
+    nft_mint TEXT PRIMARY KEY,
+    position TEXT NOT NULL UNIQUE,
+    pool TEXT NOT NULL,
+    token_account TEXT,
+    owner TEXT,
+    slot INTEGER,
+    updated_at DATETIME NOT NULL
+);
+CREATE INDEX idx_position_nfts_owner ON position_nfts(owner);
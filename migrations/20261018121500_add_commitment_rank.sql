@@ -0,0 +1,8 @@
+-- Commitment as an orderable rank (0 processed, 1 confirmed, 2 finalized or no longer tracked),
+-- so the commitment tracker's lookups of rows still below finalized use an index
+ALTER TABLE account_updates ADD COLUMN commitment_rank INTEGER GENERATED ALWAYS AS (
+    CASE commitment WHEN 'processed' THEN 0 WHEN 'confirmed' THEN 1 ELSE 2 END
+) VIRTUAL;
+DROP INDEX IF EXISTS idx_account_updates_commitment_slot;
+CREATE INDEX idx_account_updates_pending_commitment ON account_updates(program_id, commitment_rank, slot)
+    WHERE commitment_rank < 2 AND NOT orphaned;
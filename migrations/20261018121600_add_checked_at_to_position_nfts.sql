@@ -0,0 +1,4 @@
+-- When the portfolio tracker last confirmed each NFT's holder; NULL until it first has.
+-- The tracker checks a batch at a time, never-checked NFTs first, then the longest unchecked.
+ALTER TABLE position_nfts ADD COLUMN checked_at DATETIME;
+CREATE INDEX idx_position_nfts_checked_at ON position_nfts(checked_at);
@@ -0,0 +1,8 @@
+-- Account types of each program a completed backfill covered ('*' for all of them). Once an account
+-- type has been backfilled, an account of it first seen live is new on chain, and stored as 'created'.
+CREATE TABLE program_backfills (
+    program_id TEXT NOT NULL,
+    account_type TEXT NOT NULL,
+    completed_at DATETIME NOT NULL,
+    PRIMARY KEY (program_id, account_type)
+);
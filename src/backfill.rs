@@ -0,0 +1,179 @@
+use serde_json::json;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{OptionalContext, RpcKeyedAccount};
+use solana_pubkey::Pubkey;
+use std::str::FromStr;
+use tracing::{info, warn, error, debug, instrument};
+
+use crate::config::{PipelineConfig, RpcEndpoint};
+use crate::database::NewAccountUpdate;
+use crate::filters::FilterSet;
+use crate::positions;
+use crate::processor::{ProcessorContext, content_hash};
+use crate::registry::IndexedProgram;
+use crate::valuation;
+
+#[derive(Debug, Default)]
+pub struct BackfillStats {
+    pub fetched: usize,
+    pub stored: usize,
+    // Not decodable, not an indexed account type, or older than what is already stored
+    pub skipped: usize,
+}
+
+impl std::ops::AddAssign for BackfillStats {
+    fn add_assign(&mut self, other: Self) {
+        self.fetched += other.fetched;
+        self.stored += other.stored;
+        self.skipped += other.skipped;
+    }
+}
+
+// Seed every program of the pipeline from getProgramAccounts, before its live stream starts
+#[instrument(skip(config, endpoint, context), fields(pipeline = %config.name))]
+pub async fn backfill_pipeline(
+    config: &PipelineConfig,
+    endpoint: &RpcEndpoint,
+    context: &ProcessorContext,
+) -> Result<(), ClientError> {
+    let rpc_client = RpcClient::new_with_commitment(endpoint.http_url.clone(), context.commitment.rpc_config());
+
+    for program in &config.programs {
+        let mut stats = BackfillStats::default();
+        for filter_set in config.filters.filter_sets(program) {
+            stats += backfill_program(&rpc_client, program, &filter_set, context).await?;
+        }
+        info!(
+            pipeline = %config.name,
+            program = %program.name(),
+            fetched = stats.fetched,
+            stored = stats.stored,
+            skipped = stats.skipped,
+            "✅ Backfill complete"
+        );
+
+        // From now on, accounts of these types first seen live are new on chain
+        if let Err(e) = context
+            .database
+            .record_backfill(&program.program_id().to_string(), config.filters.account_types(program))
+            .await
+        {
+            warn!(program = %program.name(), error = %e, "⚠️ Failed to record completed backfill");
+        }
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(rpc_client, program, filter_set, context), fields(program = %program.name()))]
+pub async fn backfill_program(
+    rpc_client: &RpcClient,
+    program: &IndexedProgram,
+    filter_set: &FilterSet,
+    context: &ProcessorContext,
+) -> Result<BackfillStats, ClientError> {
+    let program_id = program.program_id();
+    info!(program = %program.name(), program_id = %program_id, "📥 Fetching program accounts for backfill");
+
+    // Rows are stored at the slot the node read the snapshot at, so they never claim to be newer than they are
+    let (slot, accounts) = get_program_accounts_with_slot(rpc_client, &program_id, filter_set).await?;
+    debug!(program = %program.name(), slot, account_count = accounts.len(), "📥 Program accounts fetched");
+
+    let mut stats = BackfillStats {
+        fetched: accounts.len(),
+        ..Default::default()
+    };
+
+    for keyed_account in accounts {
+        let (Ok(pubkey), Some(account)) = (
+            Pubkey::from_str(&keyed_account.pubkey),
+            keyed_account.account.decode::<solana_account::Account>(),
+        ) else {
+            warn!(pubkey = %keyed_account.pubkey, "⚠️ Unreadable account in getProgramAccounts response, skipping");
+            stats.skipped += 1;
+            continue;
+        };
+        let Some((account_type, data_json)) = program.decode_account(&account) else {
+            stats.skipped += 1;
+            continue;
+        };
+        let (derived, mints) = context.enrich_pool(program.sqrt_price(&account)).await.unzip();
+
+        let update = NewAccountUpdate {
+            pubkey: pubkey.to_string(),
+            slot,
+            program_id: program_id.to_string(),
+            account_type,
+            owner: account.owner.to_string(),
+            lamports: account.lamports,
+            data_json,
+            write_version: None,
+            transaction_signature: None,
+            origin: "backfill".to_string(),
+            commitment: context.commitment.as_str().to_string(),
+            data_hash: content_hash(&account),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: account.data,
+            decoder_version: program.decoder_version().to_string(),
+            event_type: "updated".to_string(),
+            derived,
+            mints,
+        };
+
+        match context.database.insert_account_update_if_newer(update).await {
+            Ok(Some(account_update)) => {
+                stats.stored += 1;
+                // The live pipeline learns of the account here, so its later closure is recognised
+                context.indexed_accounts.record(&account_update);
+                if let Err(e) = context.cache.set_account(&account_update.pubkey, &account_update).await {
+                    warn!(pubkey = %account_update.pubkey, error = %e, "⚠️ Failed to cache backfilled account in Redis");
+                }
+                positions::index(context, &account_update).await;
+                valuation::revalue(context, &account_update).await;
+            }
+            Ok(None) => stats.skipped += 1,
+            Err(e) => {
+                error!(pubkey = %pubkey, error = %e, "❌ Failed to store backfilled account in database");
+                stats.skipped += 1;
+            }
+        }
+    }
+
+    debug!(program = %program.name(), stored = stats.stored, "📦 Program accounts seeded");
+    Ok(stats)
+}
+
+// getProgramAccounts with `withContext`, which the client's typed call drops; returns the slot the
+// accounts were read at alongside them
+async fn get_program_accounts_with_slot(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    filter_set: &FilterSet,
+) -> Result<(u64, Vec<RpcKeyedAccount>), ClientError> {
+    let config = RpcProgramAccountsConfig {
+        filters: filter_set.to_rpc_filters(),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(rpc_client.commitment()),
+            ..Default::default()
+        },
+        with_context: Some(true),
+        ..Default::default()
+    };
+
+    let response: OptionalContext<Vec<RpcKeyedAccount>> = rpc_client
+        .send(RpcRequest::GetProgramAccounts, json!([program_id.to_string(), config]))
+        .await?;
+    match response {
+        OptionalContext::Context(response) => Ok((response.context.slot, response.value)),
+        OptionalContext::NoContext(_) => Err(ClientErrorKind::Custom(
+            "getProgramAccounts response has no context slot".to_string(),
+        )
+        .into()),
+    }
+}
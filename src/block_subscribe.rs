@@ -0,0 +1,401 @@
+use async_trait::async_trait;
+use carbon_core::datasource::{
+    AccountUpdate, BlockDetails, Datasource, DatasourceId, TransactionUpdate, Update, UpdateType,
+};
+use carbon_core::error::{CarbonResult, Error as CarbonError};
+use carbon_core::metrics::MetricsCollection;
+use carbon_core::transformers::transaction_metadata_from_original_meta;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcBlockSubscribeConfig, RpcBlockSubscribeFilter};
+use solana_hash::Hash;
+use solana_pubkey::Pubkey;
+use solana_signature::Signature;
+use solana_transaction_status::{TransactionDetails, UiConfirmedBlock, UiTransactionEncoding};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn, error, debug, instrument};
+
+use crate::commitment::Commitment;
+use crate::database::{AccountUpdate as StoredAccountUpdate, InstructionEvent};
+use crate::filters::FilterSet;
+
+// getMultipleAccounts accepts at most 100 pubkeys per call
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+// Groups still waiting for their BlockDetails after this long are dropped
+const BLOCK_GROUP_TTL: Duration = Duration::from_secs(60);
+
+// Everything one block changed for one indexed program, delivered as a single message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BlockEvent {
+    pub slot: u64,
+    #[serde(default)]
+    pub program_id: String,
+    pub block_time: Option<i64>,
+    pub blockhash: Option<String>,
+    pub signatures: Vec<String>,
+    pub accounts: Vec<StoredAccountUpdate>,
+    pub instructions: Vec<InstructionEvent>,
+}
+
+#[derive(Debug)]
+struct PendingBlock {
+    block: BlockEvent,
+    // Touched accounts whose state hasn't been stored yet; it may be stamped with a later slot
+    awaiting: HashSet<String>,
+    started: Instant,
+}
+
+// Collects what processors store for each in-flight (program, block) until its BlockDetails arrive.
+// BlockDetails don't name the program, so each datasource queues its program id for the slot
+// while holding `details_order`; Carbon processes the details in that same order.
+#[derive(Debug, Default)]
+pub struct BlockGroups {
+    pending: Mutex<HashMap<(String, u64), PendingBlock>>,
+    finishing: Mutex<HashMap<u64, VecDeque<String>>>,
+    details_order: tokio::sync::Mutex<()>,
+}
+
+impl BlockGroups {
+    fn begin(&self, program_id: &Pubkey, slot: u64, block_time: Option<i64>, blockhash: &str, signatures: Vec<String>, awaiting: HashSet<String>) {
+        let mut pending = self.pending.lock().unwrap();
+        self.evict_stale(&mut pending);
+
+        pending.insert(
+            (program_id.to_string(), slot),
+            PendingBlock {
+                block: BlockEvent {
+                    slot,
+                    program_id: program_id.to_string(),
+                    block_time,
+                    blockhash: Some(blockhash.to_string()),
+                    signatures,
+                    ..Default::default()
+                },
+                awaiting,
+                started: Instant::now(),
+            },
+        );
+    }
+
+    // Joins the latest pending block of the program that touched the account at or before its slot
+    pub fn add_account(&self, update: &StoredAccountUpdate) {
+        let mut pending = self.pending.lock().unwrap();
+        let group = pending
+            .iter_mut()
+            .filter(|((program_id, slot), group)| {
+                program_id == &update.program_id && *slot <= update.slot as u64 && group.awaiting.contains(&update.pubkey)
+            })
+            .max_by_key(|((_, slot), _)| *slot)
+            .map(|(_, group)| group);
+
+        if let Some(group) = group {
+            group.awaiting.remove(&update.pubkey);
+            group.block.accounts.push(update.clone());
+        }
+    }
+
+    pub fn add_instruction(&self, event: &InstructionEvent) {
+        if let Some(group) = self.pending.lock().unwrap().get_mut(&(event.program_id.clone(), event.slot as u64)) {
+            group.block.instructions.push(event.clone());
+        }
+    }
+
+    // Send the program's BlockDetails for the slot, recording whose they are
+    async fn send_details(
+        &self,
+        program_id: &Pubkey,
+        slot: u64,
+        sender: &Sender<(Update, DatasourceId)>,
+        details: BlockDetails,
+        id: &DatasourceId,
+    ) -> CarbonResult<()> {
+        let _order = self.details_order.lock().await;
+        self.finishing
+            .lock()
+            .unwrap()
+            .entry(slot)
+            .or_default()
+            .push_back(program_id.to_string());
+        send(sender, Update::BlockDetails(details), id).await
+    }
+
+    // Called once per BlockDetails, in the order they were sent
+    pub fn finish(&self, slot: u64) -> Option<BlockEvent> {
+        let program_id = {
+            let mut finishing = self.finishing.lock().unwrap();
+            let queue = finishing.get_mut(&slot)?;
+            let program_id = queue.pop_front();
+            if queue.is_empty() {
+                finishing.remove(&slot);
+            }
+            program_id?
+        };
+        self.pending
+            .lock()
+            .unwrap()
+            .remove(&(program_id, slot))
+            .map(|group| group.block)
+    }
+
+    fn evict_stale(&self, pending: &mut HashMap<(String, u64), PendingBlock>) {
+        let before = pending.len();
+        pending.retain(|_, group| group.started.elapsed() < BLOCK_GROUP_TTL);
+        let evicted = before - pending.len();
+        if evicted > 0 {
+            warn!(evicted, "⚠️ Dropping block groups whose BlockDetails never arrived");
+            let live_slots: HashSet<u64> = pending.keys().map(|(_, slot)| *slot).collect();
+            self.finishing.lock().unwrap().retain(|slot, _| live_slots.contains(slot));
+        }
+    }
+}
+
+// Consumes blockSubscribe for one program and turns each block into grouped Carbon updates:
+// the program accounts its transactions touched, the transactions themselves, then its BlockDetails
+pub struct BlockSubscribeDatasource {
+    rpc_ws_url: String,
+    rpc_client: RpcClient,
+    program_id: Pubkey,
+    // Applied to fetched accounts, since blocks can't be filtered by account data
+    filter_sets: Vec<FilterSet>,
+    commitment: Commitment,
+    block_groups: Arc<BlockGroups>,
+}
+
+impl BlockSubscribeDatasource {
+    pub fn new(
+        rpc_ws_url: String,
+        rpc_http_url: String,
+        program_id: Pubkey,
+        filter_sets: Vec<FilterSet>,
+        commitment: Commitment,
+        block_groups: Arc<BlockGroups>,
+    ) -> Self {
+        Self {
+            rpc_ws_url,
+            rpc_client: RpcClient::new(rpc_http_url),
+            program_id,
+            filter_sets,
+            // Blocks are at least confirmed, and their accounts are read at the same commitment
+            commitment: commitment.for_blocks(),
+            block_groups,
+        }
+    }
+
+    #[instrument(skip(self, block, sender, id), fields(program_id = %self.program_id, slot))]
+    async fn emit_block(
+        &self,
+        slot: u64,
+        block: UiConfirmedBlock,
+        sender: &Sender<(Update, DatasourceId)>,
+        id: &DatasourceId,
+    ) -> CarbonResult<()> {
+        let block_hash = Hash::from_str(&block.blockhash).ok();
+        let mut transactions = Vec::new();
+        // Program accounts touched in this block, with the last transaction that touched each
+        let mut touched: HashMap<Pubkey, Signature> = HashMap::new();
+
+        for encoded in block.transactions.into_iter().flatten() {
+            let (Some(transaction), Some(meta)) = (encoded.transaction.decode(), encoded.meta) else {
+                continue;
+            };
+            let Ok(meta) = transaction_metadata_from_original_meta(meta) else {
+                continue;
+            };
+            let Some(signature) = transaction.signatures.first().copied() else {
+                continue;
+            };
+
+            // Failed transactions changed no state and aren't trades
+            if meta.status.is_err() {
+                continue;
+            }
+
+            let account_keys: Vec<Pubkey> = transaction
+                .message
+                .static_account_keys()
+                .iter()
+                .chain(meta.loaded_addresses.writable.iter())
+                .chain(meta.loaded_addresses.readonly.iter())
+                .copied()
+                .collect();
+
+            let inner = meta
+                .inner_instructions
+                .iter()
+                .flatten()
+                .flat_map(|inner| inner.instructions.iter().map(|inner| &inner.instruction));
+            for instruction in transaction.message.instructions().iter().chain(inner) {
+                if account_keys.get(instruction.program_id_index as usize) != Some(&self.program_id) {
+                    continue;
+                }
+                for &index in &instruction.accounts {
+                    if let Some(pubkey) = account_keys.get(index as usize) {
+                        touched.insert(*pubkey, signature);
+                    }
+                }
+            }
+
+            transactions.push(TransactionUpdate {
+                signature,
+                transaction,
+                meta,
+                is_vote: false,
+                slot,
+                block_time: block.block_time,
+                block_hash,
+            });
+        }
+
+        self.block_groups.begin(
+            &self.program_id,
+            slot,
+            block.block_time,
+            &block.blockhash,
+            transactions.iter().map(|transaction| transaction.signature.to_string()).collect(),
+            touched.keys().map(Pubkey::to_string).collect(),
+        );
+
+        // Account states as of (at least) this block, since blocks carry no account data
+        let pubkeys: Vec<Pubkey> = touched.keys().copied().collect();
+        for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+            let response = self
+                .rpc_client
+                .get_multiple_accounts_with_config(
+                    chunk,
+                    RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        commitment: Some(self.commitment.rpc_config()),
+                        min_context_slot: Some(slot),
+                        ..Default::default()
+                    },
+                )
+                .await
+                .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+            // The state returned may be newer than the block, so it is stamped with the slot it was read at
+            let context_slot = response.context.slot;
+
+            for (pubkey, account) in chunk.iter().zip(response.value) {
+                let account = match account {
+                    Some(account) if account.owner == self.program_id => {
+                        if !self.filter_sets.iter().any(|filter_set| filter_set.matches(&account.data)) {
+                            continue;
+                        }
+                        account
+                    }
+                    // Gone after this block; the closure processor tombstones it if it was indexed
+                    None => solana_account::Account::default(),
+                    Some(_) => continue,
+                };
+                let update = Update::Account(AccountUpdate {
+                    pubkey: *pubkey,
+                    account,
+                    slot: context_slot,
+                    transaction_signature: touched.get(pubkey).copied(),
+                });
+                send(sender, update, id).await?;
+            }
+        }
+
+        debug!(slot, transaction_count = transactions.len(), account_count = pubkeys.len(), "🧱 Emitting block updates");
+        for transaction in transactions {
+            send(sender, Update::Transaction(Box::new(transaction)), id).await?;
+        }
+
+        self.block_groups
+            .send_details(
+                &self.program_id,
+                slot,
+                sender,
+                BlockDetails {
+                    slot,
+                    block_hash,
+                    previous_block_hash: Hash::from_str(&block.previous_blockhash).ok(),
+                    rewards: None,
+                    num_reward_partitions: block.num_reward_partitions,
+                    block_time: block.block_time,
+                    block_height: block.block_height,
+                },
+                id,
+            )
+            .await
+    }
+}
+
+#[async_trait]
+impl Datasource for BlockSubscribeDatasource {
+    #[instrument(skip_all, fields(program_id = %self.program_id))]
+    async fn consume(
+        &self,
+        id: DatasourceId,
+        sender: Sender<(Update, DatasourceId)>,
+        cancellation_token: CancellationToken,
+        _metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let client = PubsubClient::new(&self.rpc_ws_url)
+            .await
+            .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+
+        let (mut blocks, _unsubscribe) = client
+            .block_subscribe(
+                RpcBlockSubscribeFilter::MentionsAccountOrProgram(self.program_id.to_string()),
+                Some(RpcBlockSubscribeConfig {
+                    commitment: Some(self.commitment.rpc_config()),
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    transaction_details: Some(TransactionDetails::Full),
+                    show_rewards: Some(false),
+                    max_supported_transaction_version: Some(0),
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+        info!(program_id = %self.program_id, "🧱 Subscribed to blocks mentioning program");
+
+        loop {
+            let response = tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    info!("🛑 Block subscribe datasource cancelled");
+                    return Ok(());
+                }
+                response = blocks.next() => response,
+            };
+
+            let Some(response) = response else {
+                warn!("⚠️ Block subscription closed by server");
+                return Err(CarbonError::FailedToConsumeDatasource(
+                    "Block subscription closed".to_string(),
+                ));
+            };
+
+            let slot = response.value.slot;
+            let Some(block) = response.value.block else {
+                if let Some(err) = response.value.err {
+                    error!(slot, error = ?err, "❌ Block notification carried an error");
+                }
+                continue;
+            };
+
+            self.emit_block(slot, block, &sender, &id).await?;
+        }
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::AccountUpdate, UpdateType::Transaction, UpdateType::BlockDetails]
+    }
+}
+
+async fn send(sender: &Sender<(Update, DatasourceId)>, update: Update, id: &DatasourceId) -> CarbonResult<()> {
+    sender
+        .send((update, id.clone()))
+        .await
+        .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))
+}
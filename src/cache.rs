@@ -1,7 +1,54 @@
-use redis::{AsyncCommands, Client, RedisResult};
+use redis::{AsyncCommands, Client, RedisResult, Script};
+use std::sync::LazyLock;
 use tracing::{info, warn, error, debug, instrument};
 
 use crate::database::AccountUpdate;
+use crate::mints::MintMetadata;
+
+const ACCOUNT_TTL_SECONDS: u64 = 3600;
+// Matches how long stored mint metadata counts as fresh
+const MINT_TTL_SECONDS: u64 = 3600;
+
+// Each account:<pubkey> entry has an account_version:<pubkey> hash holding the slot and update id
+// it was written at, so the scripts compare versions without decoding the account JSON
+
+// KEYS[1] account key, KEYS[2] version key; ARGV: account JSON, slot, update id, TTL.
+// Entries without a version are overwritten.
+static SET_IF_NEWER: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+        local cached_slot = tonumber(redis.call('HGET', KEYS[2], 'slot'))
+        if cached_slot and cached_slot > tonumber(ARGV[2]) then
+            return 0
+        end
+        redis.call('SET', KEYS[1], ARGV[1], 'EX', ARGV[4])
+        redis.call('HSET', KEYS[2], 'slot', ARGV[2], 'id', ARGV[3])
+        redis.call('EXPIRE', KEYS[2], ARGV[4])
+        return 1
+        "#,
+    )
+});
+
+// KEYS[1] account key, KEYS[2] version key; ARGV: orphaned update id, restored JSON (empty to evict),
+// restored slot, restored update id, TTL
+static REVERT_IF_CURRENT: LazyLock<Script> = LazyLock::new(|| {
+    Script::new(
+        r#"
+        local cached_id = tonumber(redis.call('HGET', KEYS[2], 'id'))
+        if cached_id ~= tonumber(ARGV[1]) then
+            return 0
+        end
+        if ARGV[2] == '' then
+            redis.call('DEL', KEYS[1], KEYS[2])
+        else
+            redis.call('SET', KEYS[1], ARGV[2], 'EX', ARGV[5])
+            redis.call('HSET', KEYS[2], 'slot', ARGV[3], 'id', ARGV[4])
+            redis.call('EXPIRE', KEYS[2], ARGV[5])
+        end
+        return 1
+        "#,
+    )
+});
 
 #[derive(Debug)]
 pub struct RedisCache {
@@ -22,32 +69,79 @@ impl RedisCache {
         Ok(Self { client })
     }
 
-    #[instrument(skip(self, account), fields(pubkey = %pubkey))]
-    pub async fn set_account(&self, pubkey: &str, account: &AccountUpdate) -> RedisResult<()> {
+    // A cache whose server is never reached, so every call fails; for tests that only log cache errors
+    #[cfg(test)]
+    pub fn unreachable() -> Self {
+        Self {
+            client: Client::open("redis://127.0.0.1:1").unwrap(),
+        }
+    }
+
+    // Writes only when the incoming slot is at least the cached one, so out-of-order updates
+    // can't replace newer state. Returns whether the account was written.
+    #[instrument(skip(self, account), fields(pubkey = %pubkey, slot = account.slot))]
+    pub async fn set_account(&self, pubkey: &str, account: &AccountUpdate) -> RedisResult<bool> {
         debug!(pubkey = %pubkey, "🔴 Setting account in Redis cache");
 
         let mut conn = self.client.get_async_connection().await?;
         let key = format!("account:{}", pubkey);
-        let account_json = serde_json::to_string(account).map_err(|e| {
-            error!(pubkey = %pubkey, error = %e, "❌ JSON serialization failed for Redis cache");
-            redis::RedisError::from((
-                redis::ErrorKind::TypeError,
-                "JSON serialization failed",
-                e.to_string(),
-            ))
-        })?;
+        let version_key = format!("account_version:{}", pubkey);
+        let account_json = to_json(pubkey, account)?;
 
-        // Explicit type annotation for Redis return value (TTL: 1 hour)
-        let _: () = conn.set_ex(&key, account_json, 3600).await?;
+        let written: bool = SET_IF_NEWER
+            .key(&key)
+            .key(&version_key)
+            .arg(account_json)
+            .arg(account.slot)
+            .arg(account.id)
+            .arg(ACCOUNT_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await?;
 
-        info!(
-            pubkey = %pubkey,
-            ttl_seconds = 3600,
-            account_type = %account.account_type,
-            "✅ Account cached in Redis successfully"
-        );
+        if written {
+            info!(
+                pubkey = %pubkey,
+                ttl_seconds = ACCOUNT_TTL_SECONDS,
+                account_type = %account.account_type,
+                "✅ Account cached in Redis successfully"
+            );
+        } else {
+            debug!(pubkey = %pubkey, slot = account.slot, "⏭️ Newer account already cached, skipping write");
+        }
 
-        Ok(())
+        Ok(written)
+    }
+
+    // Replace the cached account with `restored` (or evict it) only while the cache still holds
+    // the orphaned update, so a rollback can't clobber a newer live update
+    #[instrument(skip(self, restored), fields(pubkey = %pubkey))]
+    pub async fn revert_account(
+        &self,
+        pubkey: &str,
+        orphaned_id: i64,
+        restored: Option<&AccountUpdate>,
+    ) -> RedisResult<bool> {
+        let mut conn = self.client.get_async_connection().await?;
+        let key = format!("account:{}", pubkey);
+        let version_key = format!("account_version:{}", pubkey);
+        let (restored_json, restored_slot, restored_id) = match restored {
+            Some(account) => (to_json(pubkey, account)?, account.slot, account.id),
+            None => (String::new(), 0, 0),
+        };
+
+        let reverted: bool = REVERT_IF_CURRENT
+            .key(&key)
+            .key(&version_key)
+            .arg(orphaned_id)
+            .arg(restored_json)
+            .arg(restored_slot)
+            .arg(restored_id)
+            .arg(ACCOUNT_TTL_SECONDS)
+            .invoke_async(&mut conn)
+            .await?;
+
+        debug!(pubkey = %pubkey, orphaned_id, reverted, "⏪ Cached account revert attempted");
+        Ok(reverted)
     }
 
     #[instrument(skip(self), fields(pubkey = %pubkey))]
@@ -87,11 +181,91 @@ impl RedisCache {
         }
     }
 
+    // Cached metadata of each mint, in order; unreadable entries count as missing
+    #[instrument(skip(self, mints), fields(mint_count = mints.len()))]
+    pub async fn get_mints(&self, mints: &[String]) -> RedisResult<Vec<Option<MintMetadata>>> {
+        if mints.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut conn = self.client.get_async_connection().await?;
+        let keys: Vec<String> = mints.iter().map(|mint| format!("mint:{}", mint)).collect();
+        let values: Vec<Option<String>> = redis::cmd("MGET").arg(&keys).query_async(&mut conn).await?;
+
+        Ok(values
+            .into_iter()
+            .map(|value| value.and_then(|json| serde_json::from_str(&json).ok()))
+            .collect())
+    }
+
+    pub async fn set_mint(&self, metadata: &MintMetadata) -> RedisResult<()> {
+        let mut conn = self.client.get_async_connection().await?;
+        let key = format!("mint:{}", metadata.mint);
+        let metadata_json = serde_json::to_string(metadata).map_err(|e| {
+            redis::RedisError::from((
+                redis::ErrorKind::TypeError,
+                "JSON serialization failed",
+                e.to_string(),
+            ))
+        })?;
+
+        conn.set_ex(&key, metadata_json, MINT_TTL_SECONDS).await
+    }
+
+    // Pool membership sets carry no TTL; they mirror the pool_positions table. A set only counts as
+    // complete once seeded from SQLite, which the pool_positions_seeded:<pool> marker records.
+    pub async fn add_pool_positions(&self, pool: &str, positions: &[String]) -> RedisResult<()> {
+        if positions.is_empty() {
+            return Ok(());
+        }
+        let mut conn = self.client.get_async_connection().await?;
+        let key = format!("pool_positions:{}", pool);
+        conn.sadd(&key, positions).await
+    }
+
+    // Merge the pool's positions read from SQLite into its set and mark the set complete.
+    // Members added concurrently survive the merge.
+    pub async fn seed_pool_positions(&self, pool: &str, positions: &[String]) -> RedisResult<()> {
+        let mut conn = self.client.get_async_connection().await?;
+        let key = format!("pool_positions:{}", pool);
+        let seeded_key = format!("pool_positions_seeded:{}", pool);
+
+        let mut pipe = redis::pipe();
+        pipe.atomic();
+        if !positions.is_empty() {
+            pipe.sadd(&key, positions).ignore();
+        }
+        pipe.set(&seeded_key, 1).ignore();
+        pipe.query_async(&mut conn).await
+    }
+
+    pub async fn remove_pool_position(&self, pool: &str, position: &str) -> RedisResult<()> {
+        let mut conn = self.client.get_async_connection().await?;
+        let key = format!("pool_positions:{}", pool);
+        conn.srem(&key, position).await
+    }
+
+    // The pool's positions, or None while its set hasn't been seeded and may be partial
+    pub async fn get_pool_positions(&self, pool: &str) -> RedisResult<Option<Vec<String>>> {
+        let mut conn = self.client.get_async_connection().await?;
+        let key = format!("pool_positions:{}", pool);
+        let seeded_key = format!("pool_positions_seeded:{}", pool);
+
+        let (seeded, positions): (bool, Vec<String>) = redis::pipe()
+            .atomic()
+            .exists(&seeded_key)
+            .smembers(&key)
+            .query_async(&mut conn)
+            .await?;
+        Ok(seeded.then_some(positions))
+    }
+
     pub async fn delete_account(&self, pubkey: &str) -> RedisResult<bool> {
         let mut conn = self.client.get_async_connection().await?;
         let key = format!("account:{}", pubkey);
-        let deleted: bool = conn.del(&key).await?;
-        Ok(deleted)
+        let version_key = format!("account_version:{}", pubkey);
+        let deleted: u64 = conn.del(&[&key, &version_key]).await?;
+        Ok(deleted > 0)
     }
 
     pub async fn exists_account(&self, pubkey: &str) -> RedisResult<bool> {
@@ -108,3 +282,14 @@ impl RedisCache {
         Ok(ttl)
     }
 }
+
+fn to_json(pubkey: &str, account: &AccountUpdate) -> RedisResult<String> {
+    serde_json::to_string(account).map_err(|e| {
+        error!(pubkey = %pubkey, error = %e, "❌ JSON serialization failed for Redis cache");
+        redis::RedisError::from((
+            redis::ErrorKind::TypeError,
+            "JSON serialization failed",
+            e.to_string(),
+        ))
+    })
+}
@@ -0,0 +1,267 @@
+use async_trait::async_trait;
+use carbon_core::account::{AccountDecoder, AccountProcessorInputType, DecodedAccount};
+use carbon_core::error::Error;
+use carbon_core::metrics::MetricsCollection;
+use carbon_core::processor::Processor;
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{warn, debug, instrument};
+
+use crate::database::{AccountUpdate, Database};
+use crate::processor::ProcessorContext;
+use crate::registry::IndexedProgram;
+
+// How often the indexed accounts are reloaded from the database
+const INDEXED_ACCOUNTS_REFRESH: Duration = Duration::from_secs(60);
+
+// An account that no longer holds program state: drained to zero lamports or owned by someone else
+#[derive(Debug, Clone)]
+pub struct ClosedAccount;
+
+// Matches the accounts a program's own decoder rejects or must not store, so closures reach a processor
+pub struct ClosureDecoder {
+    program_id: Pubkey,
+}
+
+impl ClosureDecoder {
+    pub fn new(program_id: Pubkey) -> Self {
+        Self { program_id }
+    }
+}
+
+impl<'a> AccountDecoder<'a> for ClosureDecoder {
+    type AccountType = ClosedAccount;
+
+    fn decode_account(
+        &self,
+        account: &'a solana_account::Account,
+    ) -> Option<DecodedAccount<Self::AccountType>> {
+        if account.lamports != 0 && account.owner == self.program_id {
+            return None;
+        }
+
+        Some(DecodedAccount {
+            lamports: account.lamports,
+            data: ClosedAccount,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        })
+    }
+}
+
+// Pubkeys whose latest stored row is live state, with the program that stored them. The closure pipe
+// sees every update the datasource delivers, so this keeps other programs' accounts away from the database.
+#[derive(Debug, Default)]
+pub struct IndexedAccounts {
+    accounts: RwLock<HashMap<String, String>>,
+    // Until seeded the map is incomplete, and closures fall back to the database
+    seeded: AtomicBool,
+}
+
+impl IndexedAccounts {
+    // Load the live accounts of the pipeline's programs, keeping anything recorded meanwhile
+    pub async fn seed(&self, database: &Database, programs: &[IndexedProgram]) {
+        for program in programs {
+            let program_id = program.program_id().to_string();
+            let pubkeys = match database.get_indexed_pubkeys(&program_id).await {
+                Ok(pubkeys) => pubkeys,
+                Err(e) => {
+                    warn!(program = program.name(), error = %e, "⚠️ Failed to load indexed accounts, closures fall back to the database");
+                    return;
+                }
+            };
+
+            debug!(program = program.name(), account_count = pubkeys.len(), "🗂️ Indexed accounts loaded for closure tracking");
+            let mut accounts = self.accounts.write().unwrap();
+            for pubkey in pubkeys {
+                accounts.entry(pubkey).or_insert_with(|| program_id.clone());
+            }
+        }
+        self.seeded.store(true, Ordering::Release);
+    }
+
+    // Track a stored row: live state adds the pubkey, a tombstone drops it
+    pub fn record(&self, update: &AccountUpdate) {
+        let mut accounts = self.accounts.write().unwrap();
+        if update.is_tombstone() {
+            accounts.remove(&update.pubkey);
+        } else {
+            accounts.insert(update.pubkey.clone(), update.program_id.clone());
+        }
+    }
+
+    // Whether the pubkey may hold live state of the program; true until seeded
+    pub fn may_hold(&self, program_id: &str, pubkey: &str) -> bool {
+        if !self.seeded.load(Ordering::Acquire) {
+            return true;
+        }
+        self.accounts.read().unwrap().get(pubkey).is_some_and(|stored| stored == program_id)
+    }
+}
+
+// Reload the map now and then, picking up accounts stored by other processes, such as a `backfill` run
+pub async fn refresh_indexed_accounts(programs: Vec<IndexedProgram>, context: ProcessorContext) {
+    let mut interval = tokio::time::interval(INDEXED_ACCOUNTS_REFRESH);
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        context.indexed_accounts.seed(&context.database, &programs).await;
+    }
+}
+
+// Turns closures of indexed accounts into tombstone rows; other accounts are ignored
+pub struct ClosureProcessor {
+    context: ProcessorContext,
+    program: IndexedProgram,
+}
+
+impl ClosureProcessor {
+    pub fn new(context: ProcessorContext, program: IndexedProgram) -> Self {
+        Self { context, program }
+    }
+}
+
+#[async_trait]
+impl Processor for ClosureProcessor {
+    type InputType = AccountProcessorInputType<ClosedAccount>;
+
+    #[instrument(skip(self, input, _metrics), fields(program = self.program.name(), pubkey = %input.0.pubkey, slot = input.0.slot))]
+    async fn process(
+        &mut self,
+        input: Self::InputType,
+        _metrics: Arc<MetricsCollection>,
+    ) -> Result<(), Error> {
+        let (metadata, _, solana_account) = input;
+
+        let pubkey = metadata.pubkey.to_string();
+        if !self
+            .context
+            .indexed_accounts
+            .may_hold(&self.program.program_id().to_string(), &pubkey)
+        {
+            return Ok(());
+        }
+
+        self.context
+            .record_closure(
+                &self.program,
+                &pubkey,
+                metadata.slot,
+                solana_account,
+                metadata.transaction_signature.map(|signature| signature.to_string()),
+                "live",
+            )
+            .await;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::prelude::{BASE64_STANDARD, Engine};
+    use carbon_core::account::AccountMetadata;
+    use serde_json::json;
+    use solana_client::nonblocking::rpc_client::RpcClient;
+    use solana_client::rpc_request::RpcRequest;
+
+    use crate::backfill;
+    use crate::commitment::Commitment;
+    use crate::filters::FilterSet;
+    use crate::test_support::{self, counter_data, counter_program};
+
+    fn closed_input(pubkey: Pubkey, slot: u64, account: solana_account::Account) -> AccountProcessorInputType<ClosedAccount> {
+        let decoded = DecodedAccount {
+            lamports: account.lamports,
+            data: ClosedAccount,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        };
+        let metadata = AccountMetadata {
+            slot,
+            pubkey,
+            transaction_signature: None,
+        };
+        (metadata, decoded, account)
+    }
+
+    #[tokio::test]
+    async fn records_closures_of_accounts_found_by_the_startup_backfill() {
+        let context = test_support::processor_context(Commitment::Confirmed).await;
+        let program = counter_program();
+        let pubkey = Pubkey::new_unique();
+
+        // Seeded from an empty database, as on first start, before the backfill runs
+        context.indexed_accounts.seed(&context.database, std::slice::from_ref(&program)).await;
+
+        let snapshot = json!({
+            "context": { "slot": 100 },
+            "value": [{
+                "pubkey": pubkey.to_string(),
+                "account": {
+                    "data": [BASE64_STANDARD.encode(counter_data(7)), "base64"],
+                    "executable": false,
+                    "lamports": 1_000_000,
+                    "owner": program.program_id().to_string(),
+                    "rentEpoch": 0,
+                    "space": 16
+                }
+            }]
+        });
+        let rpc_client = RpcClient::new_mock_with_mocks(
+            "succeeds".to_string(),
+            HashMap::from([(RpcRequest::GetProgramAccounts, snapshot)]),
+        );
+        let stats = backfill::backfill_program(&rpc_client, &program, &FilterSet::default(), &context)
+            .await
+            .unwrap();
+        assert_eq!(stats.stored, 1);
+
+        let drained = solana_account::Account {
+            lamports: 0,
+            data: Vec::new(),
+            owner: Pubkey::default(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mut processor = ClosureProcessor::new(context.clone(), program);
+        processor
+            .process(closed_input(pubkey, 120, drained), Arc::new(MetricsCollection::new(vec![])))
+            .await
+            .unwrap();
+
+        let latest = context.database.get_latest_account_state(&pubkey.to_string()).await.unwrap().unwrap();
+        assert_eq!(latest.event_type, "closed");
+        assert_eq!(latest.slot, 120);
+        assert!(!context.indexed_accounts.may_hold(&latest.program_id, &latest.pubkey));
+    }
+
+    #[tokio::test]
+    async fn ignores_accounts_the_program_never_stored() {
+        let context = test_support::processor_context(Commitment::Confirmed).await;
+        let program = counter_program();
+        let pubkey = Pubkey::new_unique();
+        context.indexed_accounts.seed(&context.database, std::slice::from_ref(&program)).await;
+
+        let foreign = solana_account::Account {
+            lamports: 5,
+            data: vec![1, 2, 3],
+            owner: Pubkey::new_unique(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        let mut processor = ClosureProcessor::new(context.clone(), program);
+        processor
+            .process(closed_input(pubkey, 10, foreign), Arc::new(MetricsCollection::new(vec![])))
+            .await
+            .unwrap();
+
+        assert!(context.database.get_latest_account_state(&pubkey.to_string()).await.unwrap().is_none());
+    }
+}
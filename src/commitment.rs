@@ -0,0 +1,361 @@
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_commitment_config::CommitmentConfig;
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{info, warn, debug, instrument};
+use yellowstone_grpc_proto::prelude::CommitmentLevel;
+
+use crate::config::{ConfigError, RpcEndpoint};
+use crate::database::AccountUpdate;
+use crate::fanout::FanoutEvent;
+use crate::positions;
+use crate::processor::ProcessorContext;
+use crate::valuation;
+
+// How often the tracker checks the confirmed and finalized slots
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// getBlocks serves at most this many slots per call
+const MAX_BLOCK_RANGE: u64 = 500_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Commitment {
+    Processed,
+    Confirmed,
+    Finalized,
+}
+
+// Sent to subscribers of the account once an update they received reaches a higher commitment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentUpgrade {
+    pub id: i64,
+    pub pubkey: String,
+    pub slot: i64,
+    pub commitment: String,
+}
+
+// Sent to subscribers of an account whose update came from a slot that left the chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rollback {
+    pub pubkey: String,
+    pub slot: u64,
+    // Id of the update that was orphaned
+    pub orphaned_id: i64,
+    // Latest state still on the chain, or None if nothing else is stored for the account
+    pub account: Option<AccountUpdate>,
+    #[serde(default)]
+    pub program_id: String,
+    // The orphaned update announced a new pool, so new pool subscribers of the program hear of it too
+    #[serde(default)]
+    pub new_pool: bool,
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TrackerError {
+    #[error("RPC request failed: {0}")]
+    Rpc(#[from] ClientError),
+
+    #[error("Database query failed: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl Commitment {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Commitment::Processed => "processed",
+            Commitment::Confirmed => "confirmed",
+            Commitment::Finalized => "finalized",
+        }
+    }
+
+    // Matches the commitment_rank column
+    fn rank(self) -> i64 {
+        self as i64
+    }
+
+    pub fn rpc_config(self) -> CommitmentConfig {
+        match self {
+            Commitment::Processed => CommitmentConfig::processed(),
+            Commitment::Confirmed => CommitmentConfig::confirmed(),
+            Commitment::Finalized => CommitmentConfig::finalized(),
+        }
+    }
+
+    pub fn geyser_level(self) -> CommitmentLevel {
+        match self {
+            Commitment::Processed => CommitmentLevel::Processed,
+            Commitment::Confirmed => CommitmentLevel::Confirmed,
+            Commitment::Finalized => CommitmentLevel::Finalized,
+        }
+    }
+
+    // blockSubscribe doesn't serve processed blocks
+    pub fn for_blocks(self) -> Self {
+        self.max(Commitment::Confirmed)
+    }
+}
+
+impl FromStr for Commitment {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "processed" => Ok(Commitment::Processed),
+            "confirmed" => Ok(Commitment::Confirmed),
+            "finalized" => Ok(Commitment::Finalized),
+            other => Err(ConfigError::InvalidCommitment(other.to_string())),
+        }
+    }
+}
+
+// Raises rows stored at processed once their slot is on the confirmed, then finalized, chain
+// and tells subscribers, the same way Solana clients see commitment upgrades.
+// Processed rows from slots that never made the confirmed chain are rolled back.
+// Only rows of the pipeline's own programs are looked at.
+#[instrument(skip(endpoint, program_ids, context), fields(pipeline = %context.pipeline, endpoint = %endpoint.http_url))]
+pub async fn track(endpoint: RpcEndpoint, program_ids: Vec<String>, context: ProcessorContext) {
+    let rpc_client = RpcClient::new(endpoint.http_url);
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        for commitment in [Commitment::Confirmed, Commitment::Finalized] {
+            if let Err(e) = upgrade_to(&rpc_client, &program_ids, &context, commitment).await {
+                warn!(commitment = commitment.as_str(), error = %e, "⚠️ Commitment tracking failed");
+            }
+        }
+    }
+}
+
+async fn upgrade_to(
+    rpc_client: &RpcClient,
+    program_ids: &[String],
+    context: &ProcessorContext,
+    commitment: Commitment,
+) -> Result<(), TrackerError> {
+    let reached_slot = rpc_client.get_slot_with_commitment(commitment.rpc_config()).await?;
+
+    // Rows beyond getBlocks' reach (left behind while tracking was down) can't be checked any more
+    let oldest_checkable = reached_slot.saturating_sub(MAX_BLOCK_RANGE);
+    let expired = context
+        .database
+        .expire_pending_commitments(program_ids, commitment.rank(), oldest_checkable)
+        .await?;
+    if expired > 0 {
+        warn!(expired, before_slot = oldest_checkable, commitment = commitment.as_str(), "⚠️ Marked updates too old to verify as unverified");
+    }
+
+    let pending = context
+        .database
+        .pending_commitment_slots(program_ids, commitment.rank(), reached_slot)
+        .await?;
+    let Some(&from_slot) = pending.first() else {
+        return Ok(());
+    };
+
+    // Slots the cluster skipped never show up in getBlocks
+    let blocks = rpc_client
+        .get_blocks_with_commitment(from_slot, Some(reached_slot), commitment.rpc_config())
+        .await?;
+
+    for slot in pending {
+        if blocks.binary_search(&slot).is_ok() {
+            publish_upgrades(context, program_ids, slot, commitment).await?;
+        } else if commitment == Commitment::Confirmed {
+            // Below the confirmed slot but not among its ancestors: the slot was skipped or its fork died
+            roll_back(context, program_ids, slot).await?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn publish_upgrades(
+    context: &ProcessorContext,
+    program_ids: &[String],
+    slot: u64,
+    commitment: Commitment,
+) -> Result<(), TrackerError> {
+    let upgraded = context
+        .database
+        .upgrade_commitment(program_ids, slot, commitment.as_str(), commitment.rank())
+        .await?;
+
+    for account in upgraded {
+        // Keep the cached copy in step when it is the row that was upgraded
+        if let Ok(Some(cached)) = context.cache.get_account(&account.pubkey).await {
+            if cached.id == account.id {
+                if let Err(e) = context.cache.set_account(&account.pubkey, &account).await {
+                    warn!(pubkey = %account.pubkey, error = %e, "⚠️ Failed to refresh cached commitment");
+                }
+            }
+        }
+
+        let upgrade = CommitmentUpgrade {
+            id: account.id,
+            pubkey: account.pubkey,
+            slot: account.slot,
+            commitment: account.commitment,
+        };
+        debug!(pubkey = %upgrade.pubkey, slot = upgrade.slot, commitment = %upgrade.commitment, "⬆️ Publishing commitment upgrade");
+        if let Err(e) = context.fanout.publish(&FanoutEvent::CommitmentUpgrade(upgrade)).await {
+            warn!(error = %e, "⚠️ Failed to publish commitment upgrade to gateways");
+        }
+    }
+
+    Ok(())
+}
+
+// Orphan the slot's updates, put the cache and derived state back on the last valid state and tell subscribers
+async fn roll_back(context: &ProcessorContext, program_ids: &[String], slot: u64) -> Result<(), TrackerError> {
+    for orphaned in context.database.orphan_slot(program_ids, slot).await? {
+        let pubkey = orphaned.pubkey.clone();
+        let account = context.database.get_latest_account_state(&pubkey).await?;
+
+        // Left alone if a newer live update already replaced the orphaned one in the cache
+        if let Err(e) = context.cache.revert_account(&pubkey, orphaned.id, account.as_ref()).await {
+            warn!(pubkey = %pubkey, error = %e, "⚠️ Failed to revert cached account");
+        }
+        restore_derived_state(context, &orphaned, account.as_ref()).await;
+
+        info!(pubkey = %pubkey, slot, orphaned_id = orphaned.id, "⏪ Publishing rollback");
+        let rollback = Rollback {
+            pubkey,
+            slot,
+            orphaned_id: orphaned.id,
+            account,
+            // Only first sightings of pools are announced
+            new_pool: orphaned.event_type == "created" && orphaned.account_type == "Pool",
+            program_id: orphaned.program_id,
+        };
+        if let Err(e) = context.fanout.publish(&FanoutEvent::Rollback(rollback)).await {
+            warn!(error = %e, "⚠️ Failed to publish rollback to gateways");
+        }
+    }
+
+    Ok(())
+}
+
+// Re-run the hooks fed by stored rows against the state still on the chain: the closure index,
+// pool positions and NFTs, and position values. An account with no such state only existed on the
+// dead fork, so it's dropped the way a closure would drop it.
+async fn restore_derived_state(context: &ProcessorContext, orphaned: &AccountUpdate, restored: Option<&AccountUpdate>) {
+    let state = match restored {
+        Some(restored) => restored.clone(),
+        None => AccountUpdate {
+            event_type: "closed".to_string(),
+            data_json: serde_json::Value::Null,
+            data_base64: None,
+            derived: None,
+            mints: None,
+            ..orphaned.clone()
+        },
+    };
+
+    context.indexed_accounts.record(&state);
+    positions::index(context, &state).await;
+    valuation::revalue(context, &state).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use solana_pubkey::Pubkey;
+
+    use crate::database::NewAccountUpdate;
+    use crate::registry::IndexedProgram;
+    use crate::test_support::{self, counter_data, counter_program, new_update, position_data};
+
+    fn meteora_program_ids() -> Vec<String> {
+        vec![IndexedProgram::MeteoraDammV2.program_id().to_string()]
+    }
+
+    fn position_account(pool: &Pubkey, nft_mint: &Pubkey) -> solana_account::Account {
+        solana_account::Account {
+            lamports: 1_000_000,
+            data: position_data(pool, nft_mint),
+            owner: IndexedProgram::MeteoraDammV2.program_id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn rolling_back_a_fork_only_position_drops_what_it_indexed() {
+        let context = test_support::processor_context(Commitment::Processed).await;
+        let program = IndexedProgram::MeteoraDammV2;
+        context.indexed_accounts.seed(&context.database, std::slice::from_ref(&program)).await;
+        let (position, pool, nft_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        context
+            .store_and_publish(new_update(&program, &position, 50, position_account(&pool, &nft_mint)))
+            .await
+            .unwrap();
+        assert_eq!(
+            context.database.get_pool_positions(&pool.to_string()).await.unwrap(),
+            vec![position.to_string()]
+        );
+
+        roll_back(&context, &meteora_program_ids(), 50).await.unwrap();
+
+        let database = &context.database;
+        assert!(database.get_latest_account_state(&position.to_string()).await.unwrap().is_none());
+        assert!(database.get_pool_positions(&pool.to_string()).await.unwrap().is_empty());
+        assert!(database.get_position_nfts_to_check(Utc::now() + chrono::Duration::days(1), 100).await.unwrap().is_empty());
+        assert!(!context.indexed_accounts.may_hold(&program.program_id().to_string(), &position.to_string()));
+    }
+
+    #[tokio::test]
+    async fn rolling_back_a_fork_only_closure_restores_the_position() {
+        let context = test_support::processor_context(Commitment::Processed).await;
+        let program = IndexedProgram::MeteoraDammV2;
+        context.indexed_accounts.seed(&context.database, std::slice::from_ref(&program)).await;
+        let (position, pool, nft_mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+
+        let live = new_update(&program, &position, 40, position_account(&pool, &nft_mint));
+        let tombstone = NewAccountUpdate {
+            slot: 50,
+            lamports: 0,
+            data_json: serde_json::Value::Null,
+            data: Vec::new(),
+            event_type: "closed".to_string(),
+            ..live.clone()
+        };
+        context.store_and_publish(live).await.unwrap();
+        context.store_and_publish(tombstone).await.unwrap();
+        assert!(context.database.get_pool_positions(&pool.to_string()).await.unwrap().is_empty());
+
+        roll_back(&context, &meteora_program_ids(), 50).await.unwrap();
+
+        let restored = context.database.get_latest_account_state(&position.to_string()).await.unwrap().unwrap();
+        assert_eq!(restored.slot, 40);
+        assert_eq!(
+            context.database.get_pool_positions(&pool.to_string()).await.unwrap(),
+            vec![position.to_string()]
+        );
+        assert!(context.indexed_accounts.may_hold(&program.program_id().to_string(), &position.to_string()));
+    }
+
+    #[tokio::test]
+    async fn rolls_back_only_the_pipeline_programs() {
+        let context = test_support::processor_context(Commitment::Processed).await;
+        let program = counter_program();
+        let pubkey = Pubkey::new_unique();
+        let account = solana_account::Account {
+            lamports: 1_000_000,
+            data: counter_data(3),
+            owner: program.program_id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        context.store_and_publish(new_update(&program, &pubkey, 50, account)).await.unwrap();
+
+        // Another pipeline's tracker, following only Meteora DAMM V2
+        roll_back(&context, &meteora_program_ids(), 50).await.unwrap();
+
+        assert!(context.database.get_latest_account_state(&pubkey.to_string()).await.unwrap().is_some());
+    }
+}
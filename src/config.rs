@@ -1,13 +1,100 @@
 use std::env;
+use std::str::FromStr;
+
+use crate::commitment::Commitment;
+use crate::filters::{AccountFilters, MemcmpFilter, PipelineFilters};
+use crate::registry::IndexedProgram;
 
 #[derive(Clone, Debug)]
 pub struct ServiceConfig {
-    pub rpc_url: String,
+    pub command: Command,
+    pub run_mode: RunMode,
+    pub pipelines: Vec<PipelineConfig>,
     pub websocket: WebSocketConfig,
     pub redis: RedisConfig,
     pub database: DatabaseConfig,
 }
 
+// Which roles this process plays: the single RPC-consuming ingestor, a read-serving gateway, or both
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RunMode {
+    Ingest,
+    Gateway,
+    All,
+}
+
+// What the process does once configured: serve as usual, or run a maintenance task and exit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Run,
+    Backfill,
+    Redecode(RedecodeConfig),
+}
+
+// Re-runs the current decoders over stored raw account data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RedecodeConfig {
+    pub batch_size: u64,
+    // Keep the new JSON in account_update_versions instead of replacing data_json
+    pub new_version: bool,
+}
+
+// One independently configured Carbon pipeline; a process may run several
+#[derive(Clone, Debug)]
+pub struct PipelineConfig {
+    pub name: String,
+    // Failover order; the supervisor moves to the next healthy endpoint when one fails
+    pub endpoints: Vec<RpcEndpoint>,
+    pub programs: Vec<IndexedProgram>,
+    pub index_instructions: bool,
+    pub datasource: DatasourceConfig,
+    pub reconnect: ReconnectConfig,
+    // Seed account state from getProgramAccounts before the live stream first starts
+    pub backfill_on_startup: bool,
+    pub filters: PipelineFilters,
+    // At processed, stored updates are upgraded as their slots are confirmed and finalized,
+    // and rolled back when their slots leave the chain
+    pub commitment: Commitment,
+    pub dedup: DedupConfig,
+}
+
+// Skipping of redelivered accounts whose content did not change
+#[derive(Clone, Copy, Debug)]
+pub struct DedupConfig {
+    pub enabled: bool,
+    // Record the slot a skipped account was seen at on its latest row
+    pub heartbeat: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct RpcEndpoint {
+    pub ws_url: String,
+    pub http_url: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReconnectConfig {
+    pub initial_backoff_ms: u64,
+    pub max_backoff_ms: u64,
+}
+
+// Where a pipeline's account updates come from
+#[derive(Clone, Debug)]
+pub enum DatasourceConfig {
+    RpcProgramSubscribe,
+    Yellowstone(YellowstoneConfig),
+    // Accounts and instructions grouped per block, from blockSubscribe
+    BlockSubscribe,
+}
+
+#[derive(Clone, Debug)]
+pub struct YellowstoneConfig {
+    pub endpoint: String,
+    pub x_token: Option<String>,
+    // Extra accounts streamed by pubkey, on top of everything owned by the indexed programs
+    pub accounts: Vec<String>,
+}
+
 #[derive(Clone, Debug)]
 pub struct WebSocketConfig {
     pub host: String,
@@ -17,6 +104,7 @@ pub struct WebSocketConfig {
 #[derive(Clone, Debug)]
 pub struct RedisConfig {
     pub url: String,
+    pub fanout_channel: String,
 }
 
 #[derive(Clone, Debug)]
@@ -27,8 +115,39 @@ pub struct DatabaseConfig {
 
 impl ServiceConfig {
     pub fn from_env() -> Result<Self, ConfigError> {
+        // A subcommand (`account_socket ingest`) takes precedence over RUN_MODE;
+        // `account_socket backfill` runs a one-off backfill of every pipeline instead, and
+        // `account_socket redecode [--new-version]` re-decodes stored rows with the current decoders
+        let (command, run_mode) = match env::args().nth(1).as_deref() {
+            Some("backfill") => (Command::Backfill, RunMode::Ingest),
+            Some("redecode") => (
+                Command::Redecode(RedecodeConfig {
+                    batch_size: env::var("REDECODE_BATCH_SIZE")
+                        .unwrap_or_else(|_| "500".to_string())
+                        .parse::<u64>()
+                        .map_err(|_| ConfigError::InvalidNumber("REDECODE_BATCH_SIZE"))?
+                        .max(1),
+                    new_version: env::args().skip(2).any(|arg| arg == "--new-version"),
+                }),
+                RunMode::Ingest,
+            ),
+            Some(arg) => (Command::Run, arg.parse()?),
+            None => (
+                Command::Run,
+                env::var("RUN_MODE").unwrap_or_else(|_| "all".to_string()).parse()?,
+            ),
+        };
+
+        let pipelines = if run_mode.runs_ingest() {
+            PipelineConfig::all_from_env()?
+        } else {
+            Vec::new()
+        };
+
         Ok(Self {
-            rpc_url: env::var("RPC_URL").map_err(|_| ConfigError::MissingEnvVar("RPC_URL"))?,
+            command,
+            run_mode,
+            pipelines,
             websocket: WebSocketConfig {
                 host: env::var("WEBSOCKET_HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
                 port: env::var("WEBSOCKET_PORT")
@@ -38,6 +157,7 @@ impl ServiceConfig {
             },
             redis: RedisConfig {
                 url: env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string()),
+                fanout_channel: env::var("REDIS_FANOUT_CHANNEL").unwrap_or_else(|_| "account_updates".to_string()),
             },
             database: DatabaseConfig {
                 url: env::var("DATABASE_URL").map_err(|_| ConfigError::MissingEnvVar("DATABASE_URL"))?,
@@ -50,6 +170,255 @@ impl ServiceConfig {
     }
 }
 
+impl PipelineConfig {
+    // PIPELINES lists pipeline names; each reads PIPELINE_<NAME>_* and falls back to the global variables
+    fn all_from_env() -> Result<Vec<Self>, ConfigError> {
+        let names = match env::var("PIPELINES") {
+            Ok(names) => names
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect(),
+            Err(_) => vec!["default".to_string()],
+        };
+
+        names.into_iter().map(Self::from_env).collect()
+    }
+
+    fn from_env(name: String) -> Result<Self, ConfigError> {
+        // RPC_URLS lists failover endpoints; RPC_URL is a single endpoint
+        let ws_urls = endpoint_urls(&name, "RPC_URLS", "RPC_URL")
+            .ok_or(ConfigError::MissingEnvVar("RPC_URL"))?;
+        // HTTP requests go to the matching RPC_HTTP_URLS entry, or the websocket endpoint with an http(s) scheme
+        let http_urls = endpoint_urls(&name, "RPC_HTTP_URLS", "RPC_HTTP_URL").unwrap_or_default();
+        let endpoints = ws_urls
+            .into_iter()
+            .enumerate()
+            .map(|(index, ws_url)| RpcEndpoint {
+                http_url: http_urls.get(index).cloned().unwrap_or_else(|| {
+                    ws_url
+                        .replacen("wss://", "https://", 1)
+                        .replacen("ws://", "http://", 1)
+                }),
+                ws_url,
+            })
+            .collect();
+
+        // Each listed program gets its own program subscription within the pipeline
+        let programs = pipeline_or_global(&name, "PROGRAMS")
+            .unwrap_or_else(|| "meteora_damm_v2".to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|program| !program.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<IndexedProgram>, _>>()?;
+
+        let index_instructions = pipeline_or_global(&name, "INDEX_INSTRUCTIONS")
+            .map(|value| parse_bool(&value, "INDEX_INSTRUCTIONS"))
+            .transpose()?
+            .unwrap_or(false);
+
+        let datasource = match pipeline_or_global(&name, "DATASOURCE").as_deref() {
+            None | Some("rpc_program_subscribe") => DatasourceConfig::RpcProgramSubscribe,
+            Some("yellowstone") => DatasourceConfig::Yellowstone(YellowstoneConfig::from_env(&name)?),
+            Some("block_subscribe") => DatasourceConfig::BlockSubscribe,
+            Some(other) => return Err(ConfigError::InvalidDatasource(other.to_string())),
+        };
+
+        let reconnect = ReconnectConfig {
+            initial_backoff_ms: number_var(&name, "RECONNECT_INITIAL_BACKOFF_MS", 500)?,
+            max_backoff_ms: number_var(&name, "RECONNECT_MAX_BACKOFF_MS", 30_000)?,
+        };
+
+        let backfill_on_startup = pipeline_or_global(&name, "BACKFILL_ON_STARTUP")
+            .map(|value| parse_bool(&value, "BACKFILL_ON_STARTUP"))
+            .transpose()?
+            .unwrap_or(false);
+
+        let filters = PipelineFilters::new(
+            programs
+                .iter()
+                .map(|program| Ok((program.program_id(), program_filters(&name, program)?)))
+                .collect::<Result<_, ConfigError>>()?,
+        );
+
+        let track_commitment = pipeline_or_global(&name, "TRACK_COMMITMENT")
+            .map(|value| parse_bool(&value, "TRACK_COMMITMENT"))
+            .transpose()?
+            .unwrap_or(false);
+        // YELLOWSTONE_COMMITMENT is the older name for COMMITMENT. Unset, RPC datasources keep the
+        // RPC default (finalized) and Yellowstone its former default (confirmed).
+        let configured_commitment = pipeline_or_global(&name, "COMMITMENT")
+            .or_else(|| pipeline_or_global(&name, "YELLOWSTONE_COMMITMENT"))
+            .map(|value| value.parse::<Commitment>())
+            .transpose()?;
+        // TRACK_COMMITMENT is shorthand for processed
+        let commitment = match (track_commitment, configured_commitment) {
+            (true, None | Some(Commitment::Processed)) => Commitment::Processed,
+            (true, Some(other)) => return Err(ConfigError::ConflictingCommitment(other.as_str().to_string())),
+            (false, Some(commitment)) => commitment,
+            (false, None) => match datasource {
+                DatasourceConfig::Yellowstone(_) => Commitment::Confirmed,
+                DatasourceConfig::RpcProgramSubscribe | DatasourceConfig::BlockSubscribe => Commitment::Finalized,
+            },
+        };
+
+        let dedup = DedupConfig {
+            enabled: pipeline_or_global(&name, "DEDUPLICATE")
+                .map(|value| parse_bool(&value, "DEDUPLICATE"))
+                .transpose()?
+                .unwrap_or(true),
+            heartbeat: pipeline_or_global(&name, "DEDUPLICATE_HEARTBEAT")
+                .map(|value| parse_bool(&value, "DEDUPLICATE_HEARTBEAT"))
+                .transpose()?
+                .unwrap_or(false),
+        };
+
+        Ok(Self {
+            name,
+            endpoints,
+            programs,
+            index_instructions,
+            datasource,
+            reconnect,
+            backfill_on_startup,
+            filters,
+            commitment,
+            dedup,
+        })
+    }
+}
+
+impl DatasourceConfig {
+    pub fn groups_blocks(&self) -> bool {
+        matches!(self, DatasourceConfig::BlockSubscribe)
+    }
+}
+
+impl YellowstoneConfig {
+    fn from_env(pipeline: &str) -> Result<Self, ConfigError> {
+        Ok(Self {
+            endpoint: pipeline_or_global(pipeline, "YELLOWSTONE_ENDPOINT")
+                .ok_or(ConfigError::MissingEnvVar("YELLOWSTONE_ENDPOINT"))?,
+            x_token: pipeline_or_global(pipeline, "YELLOWSTONE_X_TOKEN"),
+            accounts: list_var(pipeline, "YELLOWSTONE_ACCOUNTS").unwrap_or_default(),
+        })
+    }
+}
+
+fn pipeline_var(pipeline: &str, key: &str) -> Option<String> {
+    env::var(format!("PIPELINE_{}_{}", pipeline.to_uppercase(), key)).ok()
+}
+
+fn pipeline_or_global(pipeline: &str, key: &str) -> Option<String> {
+    pipeline_var(pipeline, key).or_else(|| env::var(key).ok())
+}
+
+// Comma-separated list, None when unset or empty
+fn list_var(pipeline: &str, key: &str) -> Option<Vec<String>> {
+    pipeline_or_global(pipeline, key).and_then(split_list)
+}
+
+// Pipeline-level settings win over global ones, whether given as a list or a single URL
+fn endpoint_urls(pipeline: &str, list_key: &str, single_key: &str) -> Option<Vec<String>> {
+    pipeline_var(pipeline, list_key)
+        .and_then(split_list)
+        .or_else(|| pipeline_var(pipeline, single_key).map(|url| vec![url]))
+        .or_else(|| env::var(list_key).ok().and_then(split_list))
+        .or_else(|| env::var(single_key).ok().map(|url| vec![url]))
+}
+
+fn split_list(value: String) -> Option<Vec<String>> {
+    let values: Vec<String> = value
+        .split(',')
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect();
+    (!values.is_empty()).then_some(values)
+}
+
+fn number_var(pipeline: &str, key: &'static str, default: u64) -> Result<u64, ConfigError> {
+    pipeline_or_global(pipeline, key)
+        .map(|value| value.parse().map_err(|_| ConfigError::InvalidNumber(key)))
+        .transpose()
+        .map(|value| value.unwrap_or(default))
+}
+
+// `<offset>:<base58 bytes>`, as in the RPC memcmp filter
+// PIPELINE_<NAME>_<PROGRAM>_FILTER_* settings, each falling back to the pipeline-wide FILTER_* one.
+// Set but empty, a program's own setting clears the pipeline-wide one for that program.
+fn program_filters(pipeline: &str, program: &IndexedProgram) -> Result<AccountFilters, ConfigError> {
+    let program_key = |key: &str| format!("{}_{}", env_name(program.name()), key);
+    let var = |key: &str| pipeline_var(pipeline, &program_key(key)).or_else(|| pipeline_or_global(pipeline, key));
+
+    let filters = AccountFilters {
+        account_types: var("FILTER_ACCOUNT_TYPES").and_then(split_list).unwrap_or_default(),
+        data_size: var("FILTER_DATA_SIZE")
+            .and_then(|value| (!value.is_empty()).then_some(value))
+            .map(|value| value.parse().map_err(|_| ConfigError::InvalidNumber("FILTER_DATA_SIZE")))
+            .transpose()?,
+        memcmp: var("FILTER_MEMCMP")
+            .and_then(split_list)
+            .unwrap_or_default()
+            .iter()
+            .map(|value| parse_memcmp(value))
+            .collect::<Result<_, _>>()?,
+    };
+    filters.validate(
+        program,
+        &format!("PIPELINE_{}_{}", pipeline.to_uppercase(), program_key("FILTER_ACCOUNT_TYPES")),
+    )?;
+    Ok(filters)
+}
+
+// Program names as they appear in variable names: upper case, with anything else than letters and digits as _
+fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+fn parse_memcmp(value: &str) -> Result<MemcmpFilter, ConfigError> {
+    let invalid = || ConfigError::InvalidFilter(value.to_string());
+    let (offset, bytes) = value.split_once(':').ok_or_else(invalid)?;
+
+    Ok(MemcmpFilter {
+        offset: offset.trim().parse().map_err(|_| invalid())?,
+        bytes: bs58::decode(bytes.trim()).into_vec().map_err(|_| invalid())?,
+    })
+}
+
+fn parse_bool(value: &str, var: &'static str) -> Result<bool, ConfigError> {
+    match value {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(ConfigError::InvalidBool(var)),
+    }
+}
+
+impl RunMode {
+    pub fn runs_ingest(self) -> bool {
+        matches!(self, RunMode::Ingest | RunMode::All)
+    }
+
+    pub fn runs_gateway(self) -> bool {
+        matches!(self, RunMode::Gateway | RunMode::All)
+    }
+}
+
+impl FromStr for RunMode {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ingest" => Ok(RunMode::Ingest),
+            "gateway" => Ok(RunMode::Gateway),
+            "all" => Ok(RunMode::All),
+            other => Err(ConfigError::InvalidRunMode(other.to_string())),
+        }
+    }
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
     #[error("Missing required environment variable: {0}")]
@@ -60,4 +429,28 @@ pub enum ConfigError {
 
     #[error("Invalid number configuration for: {0}")]
     InvalidNumber(&'static str),
+
+    #[error("Invalid boolean configuration for: {0}")]
+    InvalidBool(&'static str),
+
+    #[error("Invalid run mode: {0} (expected ingest, gateway, all, backfill or redecode)")]
+    InvalidRunMode(String),
+
+    #[error("Invalid program: {0} (expected meteora_damm_v2, drift_v2 or idl:<path>)")]
+    InvalidProgram(String),
+
+    #[error("Invalid Anchor IDL {path}: {reason}")]
+    InvalidIdl { path: String, reason: String },
+
+    #[error("Invalid datasource: {0} (expected rpc_program_subscribe, yellowstone or block_subscribe)")]
+    InvalidDatasource(String),
+
+    #[error("Invalid commitment: {0} (expected processed, confirmed or finalized)")]
+    InvalidCommitment(String),
+
+    #[error("TRACK_COMMITMENT streams at processed, but COMMITMENT is {0}")]
+    ConflictingCommitment(String),
+
+    #[error("Invalid account filter: {0}")]
+    InvalidFilter(String),
 }
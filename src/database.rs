@@ -1,17 +1,51 @@
+use base64::prelude::{BASE64_STANDARD, Engine};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use solana_pubkey::Pubkey;
 use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::str::FromStr;
 use tracing::{info, warn, error, debug, instrument};
 
+use crate::mints::{MintMetadata, PoolMints};
+use crate::pricing::DerivedPrice;
+use crate::valuation::{self, PositionValue};
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountUpdate {
     pub id: i64,
     pub pubkey: String,
     pub slot: i64,
+    // Older cache entries predate the program id tag
+    #[serde(default)]
+    pub program_id: String,
     pub account_type: String,
     pub owner: String,
     pub lamports: i64,
     pub data_json: serde_json::Value,
+    #[serde(default)]
+    pub write_version: Option<i64>,
+    #[serde(default)]
+    pub transaction_signature: Option<String>,
+    #[serde(default = "live_origin")]
+    pub origin: String,
+    #[serde(default = "finalized_commitment")]
+    pub commitment: String,
+    // Raw account data, base64; only sent to clients that asked for an encoding
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_base64: Option<String>,
+    #[serde(default)]
+    pub executable: bool,
+    #[serde(default)]
+    pub rent_epoch: Option<u64>,
+    #[serde(default = "updated_event")]
+    pub event_type: String,
+    // Moved to the message's `derived` object for clients
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived: Option<DerivedPrice>,
+    // Pools' mint metadata, attached from mint_metadata when read back; also moved to the message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mints: Option<PoolMints>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -19,10 +53,145 @@ pub struct AccountUpdate {
 pub struct NewAccountUpdate {
     pub pubkey: String,
     pub slot: u64,
+    pub program_id: String,
     pub account_type: String,
     pub owner: String,
     pub lamports: u64,
     pub data_json: serde_json::Value,
+    pub write_version: Option<u64>,
+    pub transaction_signature: Option<String>,
+    pub origin: String, // "live", "backfill" or "reconciled"
+    pub commitment: String,
+    // sha256 of lamports, owner and raw data
+    pub data_hash: String,
+    pub data: Vec<u8>,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    pub decoder_version: String,
+    // "updated", "closed" or "owner_changed"; live inserts turn "updated" into "created" on a first
+    // sighting after a backfill of the account type, or the first state after a tombstone
+    pub event_type: String,
+    // Spot prices and mint metadata of Pool accounts
+    pub derived: Option<DerivedPrice>,
+    pub mints: Option<PoolMints>,
+}
+
+// Raw bytes of a stored update, as read back for re-decoding
+#[derive(Debug, Clone)]
+pub struct StoredRawAccount {
+    pub id: i64,
+    pub pubkey: String,
+    pub slot: i64,
+    pub owner: String,
+    pub lamports: i64,
+    pub data: Vec<u8>,
+    pub executable: bool,
+    pub rent_epoch: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Latest stored state of an account, as compared against the chain during reconciliation
+#[derive(Debug, Clone)]
+pub struct KnownAccount {
+    pub pubkey: String,
+    pub slot: i64,
+    pub lamports: i64,
+    pub data_json: serde_json::Value,
+    // Rows stored before content hashing have none
+    pub data_hash: Option<String>,
+}
+
+// A position NFT and its last known holder, as tracked for wallet portfolios
+#[derive(Debug, Clone)]
+pub struct PositionNft {
+    pub nft_mint: String,
+    pub position: String,
+    pub pool: String,
+    pub token_account: Option<String>,
+    pub owner: Option<String>,
+}
+
+fn live_origin() -> String {
+    "live".to_string()
+}
+
+fn finalized_commitment() -> String {
+    "finalized".to_string()
+}
+
+fn updated_event() -> String {
+    "updated".to_string()
+}
+
+fn derived_price(price_a_to_b: Option<f64>, price_b_to_a: Option<f64>) -> Option<DerivedPrice> {
+    Some(DerivedPrice {
+        price_a_to_b: price_a_to_b?,
+        price_b_to_a: price_b_to_a?,
+    })
+}
+
+impl AccountUpdate {
+    // Copy without the raw bytes, for messages to clients that didn't ask for them
+    pub fn without_raw_data(&self) -> Self {
+        Self {
+            data_base64: None,
+            ..self.clone()
+        }
+    }
+
+    // Rows recording that the account was closed or left the program; data_json is null
+    pub fn is_tombstone(&self) -> bool {
+        matches!(self.event_type.as_str(), "closed" | "owner_changed")
+    }
+
+    // The raw account as delivered, for decoding outside a pipeline; None without raw data
+    pub fn to_account(&self) -> Option<solana_account::Account> {
+        Some(solana_account::Account {
+            lamports: self.lamports as u64,
+            data: BASE64_STANDARD.decode(self.data_base64.as_ref()?).ok()?,
+            owner: Pubkey::from_str(&self.owner).ok()?,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch.unwrap_or(u64::MAX),
+        })
+    }
+}
+
+impl StoredRawAccount {
+    pub fn to_account(&self) -> Option<solana_account::Account> {
+        Some(solana_account::Account {
+            lamports: self.lamports as u64,
+            data: self.data.clone(),
+            owner: Pubkey::from_str(&self.owner).ok()?,
+            executable: self.executable,
+            rent_epoch: self.rent_epoch.map_or(u64::MAX, |epoch| epoch as u64),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstructionEvent {
+    pub id: i64,
+    pub signature: String,
+    pub slot: i64,
+    pub instruction_index: i64,
+    pub program_id: String,
+    pub instruction_type: String,
+    pub pool: Option<String>,
+    pub accounts: Vec<String>,
+    pub data_json: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewInstructionEvent {
+    pub signature: String,
+    pub slot: u64,
+    pub instruction_index: u32,
+    pub program_id: String,
+    pub instruction_type: String,
+    pub pool: Option<String>,
+    pub accounts: Vec<String>,
+    pub data_json: serde_json::Value,
 }
 
 #[derive(Debug)]
@@ -39,6 +208,17 @@ impl Database {
         Ok(Self { pool })
     }
 
+    // A migrated in-memory database; a single connection, since each one opens its own
+    #[cfg(test)]
+    pub async fn in_memory() -> Result<Self, sqlx::Error> {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await?;
+        sqlx::migrate!("./migrations").run(&pool).await?;
+        Ok(Self { pool })
+    }
+
     #[instrument(skip(self, update), fields(pubkey = %update.pubkey, account_type = %update.account_type, slot = update.slot))]
     pub async fn insert_account_update(
         &self,
@@ -49,9 +229,14 @@ impl Database {
         // Convert to i64 first to avoid temporary value issues
         let slot_i64 = update.slot as i64;
         let lamports_i64 = update.lamports as i64;
+        let write_version_i64 = update.write_version.map(|version| version as i64);
+        let rent_epoch_i64 = update.rent_epoch as i64;
+        let price_a_to_b = update.derived.map(|derived| derived.price_a_to_b);
+        let price_b_to_a = update.derived.map(|derived| derived.price_b_to_a);
 
         debug!(
             pubkey = %update.pubkey,
+            program_id = %update.program_id,
             account_type = %update.account_type,
             slot = update.slot,
             lamports = update.lamports,
@@ -60,16 +245,40 @@ impl Database {
 
         let row = sqlx::query!(
             r#"
-            INSERT INTO account_updates (pubkey, slot, account_type, owner, lamports, data_json, created_at)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
-            RETURNING id, pubkey, slot, account_type, owner, lamports, data_json, created_at
+            INSERT INTO account_updates (pubkey, slot, program_id, account_type, owner, lamports, data_json, write_version, transaction_signature, origin, commitment, data_hash, data, executable, rent_epoch, decoder_version, event_type, price_a_to_b, price_b_to_a, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16,
+                -- Its first state after a tombstone, or a first sighting once a backfill has stored
+                -- every existing account of the type, so accounts that merely predate the index aren't new
+                CASE WHEN ?17 = 'updated' AND COALESCE((
+                    SELECT event_type IN ('closed', 'owner_changed') FROM account_updates
+                    WHERE pubkey = ?1 AND NOT orphaned
+                    ORDER BY slot DESC, id DESC LIMIT 1
+                ), EXISTS (
+                    SELECT 1 FROM program_backfills
+                    WHERE program_id = ?3 AND account_type IN ('*', ?4)
+                )) THEN 'created' ELSE ?17 END,
+                ?18, ?19, ?20)
+            RETURNING id, pubkey, slot, program_id, account_type, owner, lamports, data_json, write_version, transaction_signature, origin, commitment, data, executable, rent_epoch, event_type, price_a_to_b, price_b_to_a, created_at
             "#,
             update.pubkey,
             slot_i64,
+            update.program_id,
             update.account_type,
             update.owner,
             lamports_i64,
             update.data_json,
+            write_version_i64,
+            update.transaction_signature,
+            update.origin,
+            update.commitment,
+            update.data_hash,
+            update.data,
+            update.executable,
+            rent_epoch_i64,
+            update.decoder_version,
+            update.event_type,
+            price_a_to_b,
+            price_b_to_a,
             created_at
         ).fetch_one(&self.pool).await?;
 
@@ -77,10 +286,21 @@ impl Database {
             id: row.id,
             pubkey: row.pubkey,
             slot: row.slot,
+            program_id: row.program_id,
             account_type: row.account_type,
             owner: row.owner,
             lamports: row.lamports,
             data_json: serde_json::from_str(&row.data_json).unwrap(),
+            write_version: row.write_version,
+            transaction_signature: row.transaction_signature,
+            origin: row.origin,
+            commitment: row.commitment,
+            data_base64: row.data.map(|data| BASE64_STANDARD.encode(data)),
+            executable: row.executable,
+            rent_epoch: row.rent_epoch.map(|epoch| epoch as u64),
+            event_type: row.event_type,
+            derived: derived_price(row.price_a_to_b, row.price_b_to_a),
+            mints: update.mints.clone(),
             created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
         };
 
@@ -94,6 +314,80 @@ impl Database {
         Ok(account_update)
     }
 
+    // Insert unless a row at the same or a newer slot already exists, so snapshots never replace live state
+    #[instrument(skip(self, update), fields(pubkey = %update.pubkey, account_type = %update.account_type, slot = update.slot))]
+    pub async fn insert_account_update_if_newer(
+        &self,
+        update: NewAccountUpdate,
+    ) -> Result<Option<AccountUpdate>, sqlx::Error> {
+        let created_at = Utc::now();
+
+        let slot_i64 = update.slot as i64;
+        let lamports_i64 = update.lamports as i64;
+        let write_version_i64 = update.write_version.map(|version| version as i64);
+        let rent_epoch_i64 = update.rent_epoch as i64;
+        let price_a_to_b = update.derived.map(|derived| derived.price_a_to_b);
+        let price_b_to_a = update.derived.map(|derived| derived.price_b_to_a);
+
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO account_updates (pubkey, slot, program_id, account_type, owner, lamports, data_json, write_version, transaction_signature, origin, commitment, data_hash, data, executable, rent_epoch, decoder_version, event_type, price_a_to_b, price_b_to_a, created_at)
+            SELECT ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19, ?20
+            WHERE NOT EXISTS (
+                SELECT 1 FROM account_updates WHERE pubkey = ?1 AND slot >= ?2 AND NOT orphaned
+            )
+            RETURNING id, pubkey, slot, program_id, account_type, owner, lamports, data_json, write_version, transaction_signature, origin, commitment, data, executable, rent_epoch, event_type, price_a_to_b, price_b_to_a, created_at
+            "#,
+            update.pubkey,
+            slot_i64,
+            update.program_id,
+            update.account_type,
+            update.owner,
+            lamports_i64,
+            update.data_json,
+            write_version_i64,
+            update.transaction_signature,
+            update.origin,
+            update.commitment,
+            update.data_hash,
+            update.data,
+            update.executable,
+            rent_epoch_i64,
+            update.decoder_version,
+            update.event_type,
+            price_a_to_b,
+            price_b_to_a,
+            created_at
+        ).fetch_optional(&self.pool).await?;
+
+        let Some(row) = row else {
+            debug!(pubkey = %update.pubkey, slot = update.slot, "⏭️ Newer account state already stored, skipping insert");
+            return Ok(None);
+        };
+
+        Ok(Some(AccountUpdate {
+            id: row.id,
+            pubkey: row.pubkey,
+            slot: row.slot,
+            program_id: row.program_id,
+            account_type: row.account_type,
+            owner: row.owner,
+            lamports: row.lamports,
+            data_json: serde_json::from_str(&row.data_json).unwrap(),
+            write_version: row.write_version,
+            transaction_signature: row.transaction_signature,
+            origin: row.origin,
+            commitment: row.commitment,
+            data_base64: row.data.map(|data| BASE64_STANDARD.encode(data)),
+            executable: row.executable,
+            rent_epoch: row.rent_epoch.map(|epoch| epoch as u64),
+            event_type: row.event_type,
+            derived: derived_price(row.price_a_to_b, row.price_b_to_a),
+            mints: update.mints.clone(),
+            created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+        }))
+    }
+
     #[instrument(skip(self), fields(pubkey = %pubkey))]
     pub async fn get_latest_account_state(
         &self,
@@ -103,9 +397,9 @@ impl Database {
 
         let row = sqlx::query!(
             r#"
-            SELECT id,pubkey,slot,account_type,owner,lamports,data_json,created_at
+            SELECT id,pubkey,slot,program_id,account_type,owner,lamports,data_json,write_version,transaction_signature,origin,commitment,data,executable,rent_epoch,event_type,price_a_to_b,price_b_to_a,created_at
             FROM account_updates
-            WHERE pubkey = ?1
+            WHERE pubkey = ?1 AND NOT orphaned
             ORDER BY slot DESC
             LIMIT 1
             "#,
@@ -115,14 +409,25 @@ impl Database {
         .await?;
 
         if let Some(row) = row {
-            let account_update = AccountUpdate {
+            let mut account_update = AccountUpdate {
                 id: row.id.unwrap(),
                 pubkey: row.pubkey,
                 slot: row.slot,
+                program_id: row.program_id,
                 account_type: row.account_type,
                 owner: row.owner,
                 lamports: row.lamports,
                 data_json: serde_json::from_str(&row.data_json).unwrap(),
+                write_version: row.write_version,
+                transaction_signature: row.transaction_signature,
+                origin: row.origin,
+                commitment: row.commitment,
+                data_base64: row.data.map(|data| BASE64_STANDARD.encode(data)),
+                executable: row.executable,
+                rent_epoch: row.rent_epoch.map(|epoch| epoch as u64),
+                event_type: row.event_type,
+                derived: derived_price(row.price_a_to_b, row.price_b_to_a),
+                mints: None,
                 created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
             };
 
@@ -134,10 +439,983 @@ impl Database {
                 "✅ Latest account state retrieved from database"
             );
 
+            self.attach_pool_mints(std::slice::from_mut(&mut account_update)).await?;
             Ok(Some(account_update))
         } else {
             debug!(pubkey = %pubkey, "🔍 No account state found in database");
             Ok(None)
         }
     }
+
+    // Remember that a backfill stored every existing account of these types (all types when empty)
+    #[instrument(skip(self, account_types), fields(program_id = %program_id))]
+    pub async fn record_backfill(&self, program_id: &str, account_types: &[String]) -> Result<(), sqlx::Error> {
+        let all_types = ["*".to_string()];
+        let account_types = if account_types.is_empty() { &all_types[..] } else { account_types };
+        let account_types_json = serde_json::to_string(account_types).unwrap_or_default();
+        let completed_at = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO program_backfills (program_id, account_type, completed_at)
+            SELECT ?1, value, ?3 FROM json_each(?2)
+            ON CONFLICT (program_id, account_type) DO UPDATE SET completed_at = excluded.completed_at
+            "#,
+            program_id,
+            account_types_json,
+            completed_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Id and content hash of the latest stored version of the account
+    #[instrument(skip(self), fields(pubkey = %pubkey))]
+    pub async fn get_latest_data_hash(&self, pubkey: &str) -> Result<Option<(i64, Option<String>)>, sqlx::Error> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id AS "id!: i64", data_hash
+            FROM account_updates
+            WHERE pubkey = ?1 AND NOT orphaned
+            ORDER BY slot DESC
+            LIMIT 1
+            "#,
+            pubkey
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| (row.id, row.data_hash)))
+    }
+
+    // Record that an unchanged account was seen again at a later slot
+    #[instrument(skip(self))]
+    pub async fn record_seen_slot(&self, id: i64, slot: u64) -> Result<(), sqlx::Error> {
+        let slot_i64 = slot as i64;
+
+        sqlx::query!(
+            r#"
+            UPDATE account_updates
+            SET last_seen_slot = MAX(COALESCE(last_seen_slot, slot), ?2)
+            WHERE id = ?1
+            "#,
+            id,
+            slot_i64
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Distinct slots up to max_slot holding the programs' rows below the given commitment rank
+    // (0 processed, 1 confirmed, 2 finalized)
+    #[instrument(skip(self, program_ids))]
+    pub async fn pending_commitment_slots(
+        &self,
+        program_ids: &[String],
+        rank: i64,
+        max_slot: u64,
+    ) -> Result<Vec<u64>, sqlx::Error> {
+        let program_ids_json = serde_json::to_string(program_ids).unwrap_or_default();
+        let max_slot_i64 = max_slot as i64;
+
+        // The literal rank bound lets SQLite use the partial pending-commitment index
+        let rows = sqlx::query!(
+            r#"
+            SELECT DISTINCT slot
+            FROM account_updates
+            WHERE program_id IN (SELECT value FROM json_each(?1))
+              AND commitment_rank < 2
+              AND commitment_rank < ?2
+              AND slot <= ?3
+              AND NOT orphaned
+            ORDER BY slot
+            "#,
+            program_ids_json,
+            rank,
+            max_slot_i64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.slot as u64).collect())
+    }
+
+    // Mark the programs' rows below the given rank and older than before_slot as unverified,
+    // once they are too old to check, so they stop being selected. Returns how many changed.
+    #[instrument(skip(self, program_ids))]
+    pub async fn expire_pending_commitments(
+        &self,
+        program_ids: &[String],
+        rank: i64,
+        before_slot: u64,
+    ) -> Result<u64, sqlx::Error> {
+        let program_ids_json = serde_json::to_string(program_ids).unwrap_or_default();
+        let before_slot_i64 = before_slot as i64;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE account_updates
+            SET commitment = 'unverified'
+            WHERE program_id IN (SELECT value FROM json_each(?1))
+              AND commitment_rank < 2
+              AND commitment_rank < ?2
+              AND slot < ?3
+              AND NOT orphaned
+            "#,
+            program_ids_json,
+            rank,
+            before_slot_i64
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // Raise the programs' rows at the slot to the given commitment, returning the rows that changed
+    #[instrument(skip(self))]
+    pub async fn upgrade_commitment(
+        &self,
+        program_ids: &[String],
+        slot: u64,
+        commitment: &str,
+        rank: i64,
+    ) -> Result<Vec<AccountUpdate>, sqlx::Error> {
+        let program_ids_json = serde_json::to_string(program_ids).unwrap_or_default();
+        let slot_i64 = slot as i64;
+
+        let rows = sqlx::query!(
+            r#"
+            UPDATE account_updates
+            SET commitment = ?1
+            WHERE slot = ?2
+              AND program_id IN (SELECT value FROM json_each(?4))
+              AND NOT orphaned
+              AND commitment_rank < ?3
+            RETURNING id, pubkey, slot, program_id, account_type, owner, lamports, data_json, write_version, transaction_signature, origin, commitment, data, executable, rent_epoch, event_type, price_a_to_b, price_b_to_a, created_at
+            "#,
+            commitment,
+            slot_i64,
+            rank,
+            program_ids_json
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        debug!(slot, commitment = %commitment, row_count = rows.len(), "⬆️ Account updates upgraded");
+
+        let mut upgraded: Vec<AccountUpdate> = rows
+            .into_iter()
+            .map(|row| AccountUpdate {
+                id: row.id,
+                pubkey: row.pubkey,
+                slot: row.slot,
+                program_id: row.program_id,
+                account_type: row.account_type,
+                owner: row.owner,
+                lamports: row.lamports,
+                data_json: serde_json::from_str(&row.data_json).unwrap(),
+                write_version: row.write_version,
+                transaction_signature: row.transaction_signature,
+                origin: row.origin,
+                commitment: row.commitment,
+                data_base64: row.data.map(|data| BASE64_STANDARD.encode(data)),
+                executable: row.executable,
+                rent_epoch: row.rent_epoch.map(|epoch| epoch as u64),
+                event_type: row.event_type,
+                derived: derived_price(row.price_a_to_b, row.price_b_to_a),
+                mints: None,
+                created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+            })
+            .collect();
+        self.attach_pool_mints(&mut upgraded).await?;
+        Ok(upgraded)
+    }
+
+    // Mark the programs' processed rows of a slot that left the chain, returning them
+    #[instrument(skip(self))]
+    pub async fn orphan_slot(&self, program_ids: &[String], slot: u64) -> Result<Vec<AccountUpdate>, sqlx::Error> {
+        let program_ids_json = serde_json::to_string(program_ids).unwrap_or_default();
+        let slot_i64 = slot as i64;
+
+        let rows = sqlx::query!(
+            r#"
+            UPDATE account_updates
+            SET orphaned = TRUE
+            WHERE slot = ?1
+              AND program_id IN (SELECT value FROM json_each(?2))
+              AND commitment = 'processed'
+              AND NOT orphaned
+            RETURNING id, pubkey, slot, program_id, account_type, owner, lamports, data_json, write_version, transaction_signature, origin, commitment, data, executable, rent_epoch, event_type, price_a_to_b, price_b_to_a, created_at
+            "#,
+            slot_i64,
+            program_ids_json
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        if !rows.is_empty() {
+            warn!(slot, row_count = rows.len(), "🍂 Account updates orphaned by a dead fork");
+        }
+
+        Ok(rows
+            .into_iter()
+            .map(|row| AccountUpdate {
+                id: row.id,
+                pubkey: row.pubkey,
+                slot: row.slot,
+                program_id: row.program_id,
+                account_type: row.account_type,
+                owner: row.owner,
+                lamports: row.lamports,
+                data_json: serde_json::from_str(&row.data_json).unwrap(),
+                write_version: row.write_version,
+                transaction_signature: row.transaction_signature,
+                origin: row.origin,
+                commitment: row.commitment,
+                data_base64: row.data.map(|data| BASE64_STANDARD.encode(data)),
+                executable: row.executable,
+                rent_epoch: row.rent_epoch.map(|epoch| epoch as u64),
+                event_type: row.event_type,
+                derived: derived_price(row.price_a_to_b, row.price_b_to_a),
+                mints: None,
+                created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+            })
+            .collect())
+    }
+
+    // Next batch of the program's rows with raw data not yet decoded by decoder_version, after after_id.
+    // With `versioned`, rows count as decoded once account_update_versions holds that version.
+    #[instrument(skip(self))]
+    pub async fn get_redecode_batch(
+        &self,
+        program_id: &str,
+        decoder_version: &str,
+        versioned: bool,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<StoredRawAccount>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!: i64", pubkey, slot, owner, lamports, data AS "data!: Vec<u8>", executable, rent_epoch, created_at
+            FROM account_updates a
+            WHERE program_id = ?1
+              AND id > ?4
+              AND data IS NOT NULL
+              AND event_type NOT IN ('closed', 'owner_changed')
+              AND CASE WHEN ?3
+                  THEN NOT EXISTS (
+                      SELECT 1 FROM account_update_versions v
+                      WHERE v.account_update_id = a.id AND v.decoder_version = ?2
+                  )
+                  ELSE decoder_version IS NULL OR decoder_version <> ?2
+              END
+            ORDER BY id
+            LIMIT ?5
+            "#,
+            program_id,
+            decoder_version,
+            versioned,
+            after_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| StoredRawAccount {
+                id: row.id,
+                pubkey: row.pubkey,
+                slot: row.slot,
+                owner: row.owner,
+                lamports: row.lamports,
+                data: row.data,
+                executable: row.executable,
+                rent_epoch: row.rent_epoch,
+                created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+            })
+            .collect())
+    }
+
+    // Replace a row's decoded JSON with the output of a newer decoder
+    #[instrument(skip(self, data_json))]
+    pub async fn update_decoded(
+        &self,
+        id: i64,
+        account_type: &str,
+        data_json: &serde_json::Value,
+        decoder_version: &str,
+    ) -> Result<(), sqlx::Error> {
+        let data_json = data_json.to_string();
+
+        sqlx::query!(
+            r#"
+            UPDATE account_updates
+            SET account_type = ?2, data_json = ?3, decoder_version = ?4
+            WHERE id = ?1
+            "#,
+            id,
+            account_type,
+            data_json,
+            decoder_version
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Keep a newer decoder's output next to the original row
+    #[instrument(skip(self, data_json))]
+    pub async fn insert_decoded_version(
+        &self,
+        account_update_id: i64,
+        account_type: &str,
+        data_json: &serde_json::Value,
+        decoder_version: &str,
+    ) -> Result<(), sqlx::Error> {
+        let data_json = data_json.to_string();
+
+        sqlx::query!(
+            r#"
+            INSERT OR REPLACE INTO account_update_versions (account_update_id, decoder_version, account_type, data_json)
+            VALUES (?1, ?2, ?3, ?4)
+            "#,
+            account_update_id,
+            decoder_version,
+            account_type,
+            data_json
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Pubkeys whose latest row under the program is live state, for closure tracking
+    #[instrument(skip(self))]
+    pub async fn get_indexed_pubkeys(&self, program_id: &str) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT pubkey AS "pubkey!: String"
+            FROM (
+                SELECT pubkey, event_type,
+                       ROW_NUMBER() OVER (PARTITION BY pubkey ORDER BY slot DESC, id DESC) AS rn
+                FROM account_updates
+                WHERE program_id = ?1 AND NOT orphaned
+            )
+            WHERE rn = 1 AND event_type NOT IN ('closed', 'owner_changed')
+            "#,
+            program_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.into_iter().map(|row| row.pubkey).collect())
+    }
+
+    // Every pubkey stored for the program, with its state at the highest stored slot, unless that is a tombstone
+    #[instrument(skip(self))]
+    pub async fn get_known_accounts(&self, program_id: &str) -> Result<Vec<KnownAccount>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT pubkey AS "pubkey!: String", slot AS "slot!: i64", lamports AS "lamports!: i64", data_json AS "data_json!: String", data_hash
+            FROM (
+                SELECT pubkey, slot, lamports, data_json, data_hash, event_type,
+                       ROW_NUMBER() OVER (PARTITION BY pubkey ORDER BY slot DESC, id DESC) AS rn
+                FROM account_updates
+                WHERE program_id = ?1 AND NOT orphaned
+            )
+            WHERE rn = 1 AND event_type NOT IN ('closed', 'owner_changed')
+            "#,
+            program_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        debug!(program_id = %program_id, account_count = rows.len(), "🔍 Known accounts loaded from database");
+
+        Ok(rows
+            .into_iter()
+            .map(|row| KnownAccount {
+                pubkey: row.pubkey,
+                slot: row.slot,
+                lamports: row.lamports,
+                data_json: serde_json::from_str(&row.data_json).unwrap_or(serde_json::Value::Null),
+                data_hash: row.data_hash,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self, event), fields(signature = %event.signature, instruction_type = %event.instruction_type, slot = event.slot))]
+    pub async fn insert_instruction_event(
+        &self,
+        event: NewInstructionEvent,
+    ) -> Result<InstructionEvent, sqlx::Error> {
+        let created_at = Utc::now();
+
+        let slot_i64 = event.slot as i64;
+        let instruction_index_i64 = event.instruction_index as i64;
+        let accounts_json = serde_json::Value::from(event.accounts.clone());
+
+        debug!(
+            signature = %event.signature,
+            instruction_type = %event.instruction_type,
+            pool = ?event.pool,
+            "💾 Executing database insert for instruction event"
+        );
+
+        let row = sqlx::query!(
+            r#"
+            INSERT INTO instruction_events (signature, slot, instruction_index, program_id, instruction_type, pool, accounts_json, data_json, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+            RETURNING id, created_at
+            "#,
+            event.signature,
+            slot_i64,
+            instruction_index_i64,
+            event.program_id,
+            event.instruction_type,
+            event.pool,
+            accounts_json,
+            event.data_json,
+            created_at
+        ).fetch_one(&self.pool).await?;
+
+        let instruction_event = InstructionEvent {
+            id: row.id,
+            signature: event.signature,
+            slot: slot_i64,
+            instruction_index: instruction_index_i64,
+            program_id: event.program_id,
+            instruction_type: event.instruction_type,
+            pool: event.pool,
+            accounts: event.accounts,
+            data_json: event.data_json,
+            created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+        };
+
+        info!(
+            id = instruction_event.id,
+            signature = %instruction_event.signature,
+            instruction_type = %instruction_event.instruction_type,
+            "✅ Instruction event inserted successfully into database"
+        );
+
+        Ok(instruction_event)
+    }
+
+    // Stored metadata of the given mints, skipping rows last refreshed before fresh_since
+    #[instrument(skip(self, mints), fields(mint_count = mints.len()))]
+    pub async fn get_mint_metadata(
+        &self,
+        mints: &[String],
+        fresh_since: DateTime<Utc>,
+    ) -> Result<Vec<MintMetadata>, sqlx::Error> {
+        let mints_json = serde_json::to_string(mints).unwrap_or_default();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT mint, decimals, supply, token_program, name, symbol
+            FROM mint_metadata
+            WHERE mint IN (SELECT value FROM json_each(?1)) AND updated_at >= ?2
+            "#,
+            mints_json,
+            fresh_since
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                Some(MintMetadata {
+                    mint: row.mint,
+                    decimals: row.decimals as u8,
+                    supply: row.supply.parse().ok()?,
+                    token_program: row.token_program,
+                    name: row.name,
+                    symbol: row.symbol,
+                })
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self, metadata), fields(mint = %metadata.mint))]
+    pub async fn upsert_mint_metadata(&self, metadata: &MintMetadata) -> Result<(), sqlx::Error> {
+        let decimals = metadata.decimals as i64;
+        let supply = metadata.supply.to_string();
+        let updated_at = Utc::now();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO mint_metadata (mint, decimals, supply, token_program, name, symbol, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            ON CONFLICT (mint) DO UPDATE SET
+                decimals = excluded.decimals,
+                supply = excluded.supply,
+                token_program = excluded.token_program,
+                name = excluded.name,
+                symbol = excluded.symbol,
+                updated_at = excluded.updated_at
+            "#,
+            metadata.mint,
+            decimals,
+            supply,
+            metadata.token_program,
+            metadata.name,
+            metadata.symbol,
+            updated_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Latest raw state of each pubkey that still holds program data, for decoding outside a pipeline
+    #[instrument(skip(self, pubkeys), fields(pubkey_count = pubkeys.len()))]
+    pub async fn get_latest_raw_accounts(&self, pubkeys: &[String]) -> Result<Vec<StoredRawAccount>, sqlx::Error> {
+        let pubkeys_json = serde_json::to_string(pubkeys).unwrap_or_default();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!: i64", pubkey, slot, owner, lamports, data AS "data!: Vec<u8>", executable, rent_epoch, created_at
+            FROM account_updates a
+            WHERE pubkey IN (SELECT value FROM json_each(?1))
+              AND data IS NOT NULL
+              AND event_type NOT IN ('closed', 'owner_changed')
+              AND id = (
+                  SELECT id FROM account_updates b
+                  WHERE b.pubkey = a.pubkey AND NOT b.orphaned
+                  ORDER BY slot DESC, id DESC
+                  LIMIT 1
+              )
+            "#,
+            pubkeys_json
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| StoredRawAccount {
+                id: row.id,
+                pubkey: row.pubkey,
+                slot: row.slot,
+                owner: row.owner,
+                lamports: row.lamports,
+                data: row.data,
+                executable: row.executable,
+                rent_epoch: row.rent_epoch,
+                created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+            })
+            .collect())
+    }
+
+    // Latest raw state of the program's accounts of one type, in id order, for rebuilding indexes
+    #[instrument(skip(self))]
+    pub async fn get_latest_raw_batch(
+        &self,
+        program_id: &str,
+        account_type: &str,
+        after_id: i64,
+        limit: i64,
+    ) -> Result<Vec<StoredRawAccount>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!: i64", pubkey, slot, owner, lamports, data AS "data!: Vec<u8>", executable, rent_epoch, created_at
+            FROM account_updates a
+            WHERE program_id = ?1
+              AND account_type = ?2
+              AND id > ?3
+              AND data IS NOT NULL
+              AND event_type NOT IN ('closed', 'owner_changed')
+              AND id = (
+                  SELECT id FROM account_updates b
+                  WHERE b.pubkey = a.pubkey AND NOT b.orphaned
+                  ORDER BY slot DESC, id DESC
+                  LIMIT 1
+              )
+            ORDER BY id
+            LIMIT ?4
+            "#,
+            program_id,
+            account_type,
+            after_id,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| StoredRawAccount {
+                id: row.id,
+                pubkey: row.pubkey,
+                slot: row.slot,
+                owner: row.owner,
+                lamports: row.lamports,
+                data: row.data,
+                executable: row.executable,
+                rent_epoch: row.rent_epoch,
+                created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+            })
+            .collect())
+    }
+
+    // Record a position's pool and latest valuation. Returns whether anything changed.
+    #[instrument(skip(self, value), fields(position = %position, pool = %pool))]
+    pub async fn upsert_position_value(
+        &self,
+        position: &str,
+        pool: &str,
+        slot: u64,
+        value: Option<&PositionValue>,
+    ) -> Result<bool, sqlx::Error> {
+        let slot_i64 = slot as i64;
+        let amount = |select: fn(&PositionValue) -> u64| value.map(|value| select(value) as i64);
+        let amount_a = amount(|value| value.amount_a);
+        let amount_b = amount(|value| value.amount_b);
+        let fee_a = amount(|value| value.fee_a);
+        let fee_b = amount(|value| value.fee_b);
+        let reward_0 = amount(|value| value.rewards[0]);
+        let reward_1 = amount(|value| value.rewards[1]);
+        let updated_at = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO position_values (position, pool, slot, amount_a, amount_b, fee_a, fee_b, reward_0, reward_1, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+            ON CONFLICT (position) DO UPDATE SET
+                pool = excluded.pool,
+                slot = excluded.slot,
+                amount_a = excluded.amount_a,
+                amount_b = excluded.amount_b,
+                fee_a = excluded.fee_a,
+                fee_b = excluded.fee_b,
+                reward_0 = excluded.reward_0,
+                reward_1 = excluded.reward_1,
+                updated_at = excluded.updated_at
+            WHERE (pool, amount_a, amount_b, fee_a, fee_b, reward_0, reward_1)
+                IS NOT (excluded.pool, excluded.amount_a, excluded.amount_b, excluded.fee_a, excluded.fee_b, excluded.reward_0, excluded.reward_1)
+            "#,
+            position,
+            pool,
+            slot_i64,
+            amount_a,
+            amount_b,
+            fee_a,
+            fee_b,
+            reward_0,
+            reward_1,
+            updated_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn delete_position_value(&self, position: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!("DELETE FROM position_values WHERE position = ?1", position)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Link a position to its pool. Returns whether the position is new to the index.
+    #[instrument(skip(self), fields(position = %position, pool = %pool))]
+    pub async fn index_pool_position(&self, position: &str, pool: &str, slot: u64) -> Result<bool, sqlx::Error> {
+        let slot_i64 = slot as i64;
+        let created_at = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO pool_positions (position, pool, slot, created_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (position) DO NOTHING
+            "#,
+            position,
+            pool,
+            slot_i64,
+            created_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Drop a closed position from the index, returning the pool it belonged to
+    #[instrument(skip(self), fields(position = %position))]
+    pub async fn remove_pool_position(&self, position: &str) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query!("DELETE FROM pool_positions WHERE position = ?1 RETURNING pool", position)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|row| row.pool))
+    }
+
+    // Open positions of the pool
+    pub async fn get_pool_positions(&self, pool: &str) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!("SELECT position FROM pool_positions WHERE pool = ?1 ORDER BY position", pool)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.position).collect())
+    }
+
+    // Latest state of each pubkey, in no particular order; pubkeys without stored state are left out
+    #[instrument(skip(self, pubkeys), fields(pubkey_count = pubkeys.len()))]
+    pub async fn get_latest_account_states(&self, pubkeys: &[String]) -> Result<Vec<AccountUpdate>, sqlx::Error> {
+        let pubkeys_json = serde_json::to_string(pubkeys).unwrap_or_default();
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT id AS "id!: i64",pubkey,slot,program_id,account_type,owner,lamports,data_json,write_version,transaction_signature,origin,commitment,data,executable,rent_epoch,event_type,price_a_to_b,price_b_to_a,created_at
+            FROM account_updates a
+            WHERE pubkey IN (SELECT value FROM json_each(?1))
+              AND id = (
+                  SELECT id FROM account_updates b
+                  WHERE b.pubkey = a.pubkey AND NOT b.orphaned
+                  ORDER BY slot DESC, id DESC
+                  LIMIT 1
+              )
+            "#,
+            pubkeys_json
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut accounts: Vec<AccountUpdate> = rows
+            .into_iter()
+            .map(|row| AccountUpdate {
+                id: row.id,
+                pubkey: row.pubkey,
+                slot: row.slot,
+                program_id: row.program_id,
+                account_type: row.account_type,
+                owner: row.owner,
+                lamports: row.lamports,
+                data_json: serde_json::from_str(&row.data_json).unwrap_or(serde_json::Value::Null),
+                write_version: row.write_version,
+                transaction_signature: row.transaction_signature,
+                origin: row.origin,
+                commitment: row.commitment,
+                data_base64: row.data.map(|data| BASE64_STANDARD.encode(data)),
+                executable: row.executable,
+                rent_epoch: row.rent_epoch.map(|epoch| epoch as u64),
+                event_type: row.event_type,
+                derived: derived_price(row.price_a_to_b, row.price_b_to_a),
+                mints: None,
+                created_at: DateTime::from_naive_utc_and_offset(row.created_at.unwrap(), Utc),
+            })
+            .collect();
+        self.attach_pool_mints(&mut accounts).await?;
+        Ok(accounts)
+    }
+
+    // Stored rows don't keep mint metadata, so pools read back get it from mint_metadata, whatever its age
+    async fn attach_pool_mints(&self, accounts: &mut [AccountUpdate]) -> Result<(), sqlx::Error> {
+        let pool_mints: Vec<(usize, String, String)> = accounts
+            .iter()
+            .enumerate()
+            .filter(|(_, account)| account.mints.is_none() && account.account_type == "Pool" && !account.is_tombstone())
+            .filter_map(|(index, account)| {
+                let pool = valuation::decode_pool(&account.to_account()?)?;
+                Some((index, pool.token_a_mint.to_string(), pool.token_b_mint.to_string()))
+            })
+            .collect();
+        if pool_mints.is_empty() {
+            return Ok(());
+        }
+
+        let mints: Vec<String> = pool_mints
+            .iter()
+            .flat_map(|(_, token_a, token_b)| [token_a.clone(), token_b.clone()])
+            .collect();
+        let metadata: HashMap<String, MintMetadata> = self
+            .get_mint_metadata(&mints, DateTime::<Utc>::MIN_UTC)
+            .await?
+            .into_iter()
+            .map(|metadata| (metadata.mint.clone(), metadata))
+            .collect();
+
+        for (index, token_a, token_b) in pool_mints {
+            if let (Some(token_a), Some(token_b)) = (metadata.get(&token_a), metadata.get(&token_b)) {
+                accounts[index].mints = Some(PoolMints {
+                    token_a: token_a.clone(),
+                    token_b: token_b.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    // Remember which position an NFT mint stands for; its holder is resolved later
+    #[instrument(skip(self), fields(nft_mint = %nft_mint, position = %position))]
+    pub async fn index_position_nft(&self, nft_mint: &str, position: &str, pool: &str) -> Result<bool, sqlx::Error> {
+        let updated_at = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO position_nfts (nft_mint, position, pool, updated_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT DO NOTHING
+            "#,
+            nft_mint,
+            position,
+            pool,
+            updated_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(skip(self), fields(nft_mint = %nft_mint))]
+    pub async fn get_position_nft(&self, nft_mint: &str) -> Result<Option<PositionNft>, sqlx::Error> {
+        let row = sqlx::query!(
+            "SELECT nft_mint, position, pool, token_account, owner FROM position_nfts WHERE nft_mint = ?1",
+            nft_mint
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| PositionNft {
+            nft_mint: row.nft_mint,
+            position: row.position,
+            pool: row.pool,
+            token_account: row.token_account,
+            owner: row.owner,
+        }))
+    }
+
+    // Every NFT mint of an open position, for the holder subscriptions
+    pub async fn get_position_nft_mints(&self) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!("SELECT nft_mint FROM position_nfts ORDER BY nft_mint")
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.nft_mint).collect())
+    }
+
+    // Forget a closed position's NFT, returning it with its last holder
+    #[instrument(skip(self), fields(position = %position))]
+    pub async fn remove_position_nft(&self, position: &str) -> Result<Option<PositionNft>, sqlx::Error> {
+        let row = sqlx::query!(
+            "DELETE FROM position_nfts WHERE position = ?1 RETURNING nft_mint, position, pool, token_account, owner",
+            position
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.map(|row| PositionNft {
+            nft_mint: row.nft_mint,
+            position: row.position,
+            pool: row.pool,
+            token_account: row.token_account,
+            owner: row.owner,
+        }))
+    }
+
+    // Due every NFT for a holder check, after changes may have been missed
+    pub async fn reset_position_nft_checks(&self) -> Result<(), sqlx::Error> {
+        sqlx::query!("UPDATE position_nfts SET checked_at = NULL")
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // NFTs due for a holder check: never checked first, then those last checked before checked_before
+    #[instrument(skip(self))]
+    pub async fn get_position_nfts_to_check(
+        &self,
+        checked_before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<PositionNft>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT nft_mint, position, pool, token_account, owner
+            FROM position_nfts
+            WHERE checked_at IS NULL OR checked_at < ?1
+            ORDER BY checked_at
+            LIMIT ?2
+            "#,
+            checked_before,
+            limit
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PositionNft {
+                nft_mint: row.nft_mint,
+                position: row.position,
+                pool: row.pool,
+                token_account: row.token_account,
+                owner: row.owner,
+            })
+            .collect())
+    }
+
+    #[instrument(skip(self, nft_mints), fields(nft_count = nft_mints.len()))]
+    pub async fn mark_position_nfts_checked(&self, nft_mints: &[String]) -> Result<(), sqlx::Error> {
+        let nft_mints_json = serde_json::to_string(nft_mints).unwrap_or_default();
+        let checked_at = Utc::now();
+
+        sqlx::query!(
+            "UPDATE position_nfts SET checked_at = ?2 WHERE nft_mint IN (SELECT value FROM json_each(?1))",
+            nft_mints_json,
+            checked_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // Store the NFT's holder unless one seen at a later slot is already stored.
+    // Subscriptions and the reconciliation poll both report holders, in no particular order.
+    #[instrument(skip(self), fields(nft_mint = %nft_mint, slot))]
+    pub async fn set_position_nft_holder(
+        &self,
+        nft_mint: &str,
+        token_account: Option<&str>,
+        owner: Option<&str>,
+        slot: u64,
+    ) -> Result<bool, sqlx::Error> {
+        let slot_i64 = slot as i64;
+        let updated_at = Utc::now();
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE position_nfts
+            SET token_account = ?2, owner = ?3, slot = ?4, updated_at = ?5
+            WHERE nft_mint = ?1 AND (slot IS NULL OR slot <= ?4)
+            "#,
+            nft_mint,
+            token_account,
+            owner,
+            slot_i64,
+            updated_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // Open positions whose NFT the wallet holds
+    pub async fn get_wallet_positions(&self, owner: &str) -> Result<Vec<String>, sqlx::Error> {
+        let rows = sqlx::query!("SELECT position FROM position_nfts WHERE owner = ?1 ORDER BY position", owner)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(rows.into_iter().map(|row| row.position).collect())
+    }
 }
@@ -0,0 +1,137 @@
+use futures_util::StreamExt;
+use redis::{AsyncCommands, Client, RedisResult};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn, error, debug, instrument};
+
+use crate::block_subscribe::BlockEvent;
+use crate::commitment::{CommitmentUpgrade, Rollback};
+use crate::database::{AccountUpdate, InstructionEvent};
+use crate::portfolio::WalletPositionEvent;
+use crate::positions::PoolPositionEvent;
+use crate::registry::NewPool;
+use crate::valuation::PositionValue;
+use crate::websocket::WebSocketServer;
+
+// Delay before a gateway re-subscribes after losing its Redis pub/sub connection
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
+// Everything an ingestor stores and gateways forward to their clients.
+// Adjacently tagged so payloads aren't buffered, which loses numbers under arbitrary_precision.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "payload", rename_all = "snake_case")]
+pub enum FanoutEvent {
+    AccountUpdate(AccountUpdate),
+    InstructionEvent(InstructionEvent),
+    Block(BlockEvent),
+    CommitmentUpgrade(CommitmentUpgrade),
+    Rollback(Rollback),
+    NewPool(NewPool),
+    PositionValue(PositionValue),
+    PoolPosition(PoolPositionEvent),
+    WalletPosition(WalletPositionEvent),
+}
+
+// Carries stored events from ingestors to gateways over Redis pub/sub
+#[derive(Debug)]
+pub struct Fanout {
+    client: Client,
+    channel: String,
+}
+
+impl Fanout {
+    #[instrument(skip(redis_url))]
+    pub fn new(redis_url: &str, channel: &str) -> RedisResult<Self> {
+        let client = Client::open(redis_url)?;
+        Ok(Self {
+            client,
+            channel: channel.to_string(),
+        })
+    }
+
+    #[instrument(skip(self, event), fields(channel = %self.channel))]
+    pub async fn publish(&self, event: &FanoutEvent) -> RedisResult<()> {
+        let mut conn = self.client.get_async_connection().await?;
+        let event_json = serde_json::to_string(event).map_err(|e| {
+            error!(error = %e, "❌ JSON serialization failed for fanout");
+            redis::RedisError::from((
+                redis::ErrorKind::TypeError,
+                "JSON serialization failed",
+                e.to_string(),
+            ))
+        })?;
+
+        let receivers: i64 = conn.publish(&self.channel, event_json).await?;
+        debug!(receivers, "📣 Event published to fanout channel");
+
+        Ok(())
+    }
+
+    // Forwards every published update to this gateway's WebSocket clients, re-subscribing on failure
+    pub async fn run_subscriber(&self, websocket_server: Arc<WebSocketServer>) {
+        loop {
+            if let Err(e) = self.forward_to(&websocket_server).await {
+                error!(channel = %self.channel, error = %e, "❌ Fanout subscription failed");
+            } else {
+                warn!(channel = %self.channel, "⚠️ Fanout subscription stream ended");
+            }
+            tokio::time::sleep(RESUBSCRIBE_DELAY).await;
+        }
+    }
+
+    #[instrument(skip(self, websocket_server), fields(channel = %self.channel))]
+    async fn forward_to(&self, websocket_server: &WebSocketServer) -> RedisResult<()> {
+        let mut pubsub = self.client.get_async_connection().await?.into_pubsub();
+        pubsub.subscribe(&self.channel).await?;
+        info!(channel = %self.channel, "📻 Subscribed to fanout channel");
+
+        let mut messages = pubsub.on_message();
+        while let Some(msg) = messages.next().await {
+            let payload: String = match msg.get_payload() {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!(error = %e, "⚠️ Ignoring fanout message with unreadable payload");
+                    continue;
+                }
+            };
+
+            match serde_json::from_str::<FanoutEvent>(&payload) {
+                Ok(FanoutEvent::AccountUpdate(account)) => {
+                    websocket_server
+                        .broadcast_account_update(&account.pubkey, &account)
+                        .await;
+                }
+                Ok(FanoutEvent::InstructionEvent(event)) => {
+                    websocket_server.broadcast_trade(&event).await;
+                }
+                Ok(FanoutEvent::Block(block)) => {
+                    websocket_server.broadcast_block(&block).await;
+                }
+                Ok(FanoutEvent::CommitmentUpgrade(upgrade)) => {
+                    websocket_server.broadcast_commitment_upgrade(&upgrade).await;
+                }
+                Ok(FanoutEvent::Rollback(rollback)) => {
+                    websocket_server.broadcast_rollback(&rollback).await;
+                }
+                Ok(FanoutEvent::NewPool(new_pool)) => {
+                    websocket_server.broadcast_new_pool(&new_pool).await;
+                }
+                Ok(FanoutEvent::PositionValue(value)) => {
+                    websocket_server.broadcast_position_value(&value).await;
+                }
+                Ok(FanoutEvent::PoolPosition(event)) => {
+                    websocket_server.broadcast_pool_position(&event).await;
+                }
+                Ok(FanoutEvent::WalletPosition(event)) => {
+                    websocket_server.broadcast_wallet_position(&event).await;
+                }
+                Err(e) => {
+                    warn!(error = %e, "⚠️ Ignoring malformed fanout message");
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+
+use crate::config::ConfigError;
+use crate::registry::IndexedProgram;
+
+// Each program's account filters within a pipeline, keyed by program id
+#[derive(Clone, Debug, Default)]
+pub struct PipelineFilters {
+    by_program: HashMap<Pubkey, AccountFilters>,
+}
+
+// Server-side account filters for one program's subscriptions and backfills
+#[derive(Clone, Debug, Default)]
+pub struct AccountFilters {
+    // Presets matching each account type's discriminator at offset 0
+    pub account_types: Vec<String>,
+    pub data_size: Option<u64>,
+    pub memcmp: Vec<MemcmpFilter>,
+}
+
+#[derive(Clone, Debug)]
+pub struct MemcmpFilter {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+// Filters that must all match; one subscription (or getProgramAccounts call) is made per set
+#[derive(Clone, Debug, Default)]
+pub struct FilterSet {
+    pub data_size: Option<u64>,
+    pub memcmp: Vec<MemcmpFilter>,
+}
+
+impl PipelineFilters {
+    pub fn new(by_program: HashMap<Pubkey, AccountFilters>) -> Self {
+        Self { by_program }
+    }
+
+    // A program without filters gets a single set matching all its accounts
+    pub fn filter_sets(&self, program: &IndexedProgram) -> Vec<FilterSet> {
+        self.by_program
+            .get(&program.program_id())
+            .map(|filters| filters.filter_sets(program))
+            .unwrap_or_else(|| vec![FilterSet::default()])
+    }
+
+    pub fn account_types(&self, program: &IndexedProgram) -> &[String] {
+        self.by_program
+            .get(&program.program_id())
+            .map(|filters| filters.account_types.as_slice())
+            .unwrap_or_default()
+    }
+}
+
+impl AccountFilters {
+    // RPC filters can't express OR, so every account type preset becomes its own set,
+    // each also carrying the configured dataSize and memcmp filters
+    pub fn filter_sets(&self, program: &IndexedProgram) -> Vec<FilterSet> {
+        let shared = FilterSet {
+            data_size: self.data_size,
+            memcmp: self.memcmp.clone(),
+        };
+        if self.account_types.is_empty() {
+            return vec![shared];
+        }
+
+        self.account_types
+            .iter()
+            .filter_map(|account_type| program.account_discriminator(account_type))
+            .map(|discriminator| {
+                let mut set = shared.clone();
+                set.memcmp.insert(
+                    0,
+                    MemcmpFilter {
+                        offset: 0,
+                        bytes: discriminator.to_vec(),
+                    },
+                );
+                set
+            })
+            .collect()
+    }
+
+    // Every preset must name an account type of the program; `program_var` is the setting
+    // that gives the program its own presets, suggested when pipeline-wide ones don't fit
+    pub fn validate(&self, program: &IndexedProgram, program_var: &str) -> Result<(), ConfigError> {
+        match self
+            .account_types
+            .iter()
+            .find(|account_type| program.account_discriminator(account_type).is_none())
+        {
+            Some(account_type) => Err(ConfigError::InvalidFilter(format!(
+                "{} has no account type {}; set {} to filter it separately",
+                program.name(),
+                account_type,
+                program_var
+            ))),
+            None => Ok(()),
+        }
+    }
+}
+
+impl FilterSet {
+    pub fn to_rpc_filters(&self) -> Option<Vec<RpcFilterType>> {
+        let filters: Vec<RpcFilterType> = self
+            .data_size
+            .map(RpcFilterType::DataSize)
+            .into_iter()
+            .chain(
+                self.memcmp
+                    .iter()
+                    .map(|memcmp| RpcFilterType::Memcmp(Memcmp::new_raw_bytes(memcmp.offset, memcmp.bytes.clone()))),
+            )
+            .collect();
+        (!filters.is_empty()).then_some(filters)
+    }
+
+    // Same check the RPC node applies, for datasources that can't filter server-side
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.data_size.is_none_or(|size| data.len() as u64 == size)
+            && self.memcmp.iter().all(|memcmp| {
+                // An offset near usize::MAX from the config can't match rather than overflow
+                memcmp
+                    .offset
+                    .checked_add(memcmp.bytes.len())
+                    .and_then(|end| data.get(memcmp.offset..end))
+                    == Some(memcmp.bytes.as_slice())
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memcmp(offset: usize, bytes: &[u8]) -> MemcmpFilter {
+        MemcmpFilter {
+            offset,
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    #[test]
+    fn an_empty_set_matches_everything() {
+        let set = FilterSet::default();
+
+        assert!(set.matches(&[]));
+        assert!(set.matches(&[1, 2, 3]));
+        assert!(set.to_rpc_filters().is_none());
+    }
+
+    #[test]
+    fn matches_the_exact_data_size() {
+        let set = FilterSet {
+            data_size: Some(3),
+            memcmp: Vec::new(),
+        };
+
+        assert!(set.matches(&[1, 2, 3]));
+        assert!(!set.matches(&[1, 2]));
+        assert!(!set.matches(&[1, 2, 3, 4]));
+    }
+
+    #[test]
+    fn matches_memcmp_bytes_at_their_offset() {
+        let set = FilterSet {
+            data_size: None,
+            memcmp: vec![memcmp(0, &[1, 2]), memcmp(3, &[4])],
+        };
+
+        assert!(set.matches(&[1, 2, 3, 4]));
+        assert!(set.matches(&[1, 2, 9, 4, 5]));
+        assert!(!set.matches(&[1, 2, 3, 5]));
+        assert!(!set.matches(&[2, 2, 3, 4]));
+    }
+
+    #[test]
+    fn memcmp_past_the_end_does_not_match() {
+        let set = FilterSet {
+            data_size: None,
+            memcmp: vec![memcmp(2, &[3, 4])],
+        };
+
+        assert!(set.matches(&[1, 2, 3, 4]));
+        assert!(!set.matches(&[1, 2, 3]));
+        assert!(!set.matches(&[]));
+    }
+
+    #[test]
+    fn memcmp_offset_overflow_does_not_match() {
+        let set = FilterSet {
+            data_size: None,
+            memcmp: vec![memcmp(usize::MAX, &[1])],
+        };
+
+        assert!(!set.matches(&[1, 2, 3]));
+    }
+
+    #[test]
+    fn requires_every_filter_to_match() {
+        let set = FilterSet {
+            data_size: Some(4),
+            memcmp: vec![memcmp(0, &[1])],
+        };
+
+        assert!(set.matches(&[1, 0, 0, 0]));
+        assert!(!set.matches(&[1, 0, 0]));
+        assert!(!set.matches(&[2, 0, 0, 0]));
+        assert_eq!(set.to_rpc_filters().map(|filters| filters.len()), Some(2));
+    }
+
+    #[test]
+    fn programs_of_a_pipeline_keep_their_own_filters() {
+        let meteora = IndexedProgram::MeteoraDammV2;
+        let drift = IndexedProgram::DriftV2;
+        let pools = AccountFilters {
+            account_types: vec!["Pool".to_string()],
+            ..Default::default()
+        };
+        let filters = PipelineFilters::new(HashMap::from([(meteora.program_id(), pools)]));
+
+        let meteora_sets = filters.filter_sets(&meteora);
+        assert_eq!(meteora_sets.len(), 1);
+        assert_eq!(meteora_sets[0].memcmp[0].bytes, meteora.account_discriminator("Pool").unwrap().to_vec());
+        assert_eq!(filters.account_types(&meteora), ["Pool".to_string()]);
+
+        // Drift has no Pool account, and no filters of its own
+        assert!(filters.filter_sets(&drift)[0].to_rpc_filters().is_none());
+        assert!(filters.account_types(&drift).is_empty());
+    }
+
+    #[test]
+    fn rejects_presets_the_program_lacks() {
+        let pools = AccountFilters {
+            account_types: vec!["Pool".to_string()],
+            ..Default::default()
+        };
+
+        assert!(pools.validate(&IndexedProgram::MeteoraDammV2, "PIPELINE_DEFAULT_METEORA_DAMM_V2_FILTER_ACCOUNT_TYPES").is_ok());
+        let error = pools
+            .validate(&IndexedProgram::DriftV2, "PIPELINE_DEFAULT_DRIFT_V2_FILTER_ACCOUNT_TYPES")
+            .unwrap_err();
+        assert!(error.to_string().contains("PIPELINE_DEFAULT_DRIFT_V2_FILTER_ACCOUNT_TYPES"));
+    }
+}
@@ -0,0 +1,943 @@
+use carbon_core::account::{AccountDecoder, DecodedAccount};
+use serde_json::{Map, Number, Value};
+use sha2::{Digest, Sha256};
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::{debug, info, instrument};
+
+// Anchor prefixes every account with an 8-byte discriminator
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+// Deeper type references are assumed empty when bounding collection lengths
+const MAX_TYPE_DEPTH: usize = 32;
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdlError {
+    #[error("Failed to read IDL file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to parse IDL JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("IDL has no program address (expected `address` or `metadata.address`)")]
+    MissingAddress,
+
+    #[error("Invalid program address in IDL: {0}")]
+    InvalidAddress(String),
+
+    #[error("Unsupported IDL type: {0}")]
+    UnsupportedType(String),
+
+    #[error("IDL references undefined type: {0}")]
+    UndefinedType(String),
+
+    #[error("Account data ended early while decoding {0}")]
+    UnexpectedEof(String),
+
+    #[error("Invalid enum variant {variant} for {name}")]
+    InvalidVariant { name: String, variant: u8 },
+
+    #[error("Type {0} can't be part of a zero-copy account")]
+    NotZeroCopy(String),
+}
+
+// A program whose accounts are decoded at runtime from its Anchor IDL (both the 0.30+ and legacy formats)
+#[derive(Debug)]
+pub struct IdlProgram {
+    pub name: String,
+    pub program_id: Pubkey,
+    // Changes whenever the IDL file does, recorded as the rows' decoder version
+    pub version: String,
+    accounts: Vec<IdlAccountLayout>,
+    types: HashMap<String, IdlTypeDef>,
+}
+
+#[derive(Debug)]
+struct IdlAccountLayout {
+    name: String,
+    discriminator: [u8; DISCRIMINATOR_LEN],
+    ty: IdlTypeDef,
+}
+
+#[derive(Debug, Clone)]
+enum IdlTypeDef {
+    Struct(IdlFields),
+    // `serialization: "bytemuck"` structs: laid out as repr(C), or without padding when packed
+    ZeroCopy { fields: IdlFields, packed: bool },
+    Enum(Vec<(String, IdlFields)>),
+    Alias(IdlType),
+}
+
+#[derive(Debug, Clone)]
+enum IdlFields {
+    Named(Vec<(String, IdlType)>),
+    Tuple(Vec<IdlType>),
+    Unit,
+}
+
+#[derive(Debug, Clone)]
+enum IdlType {
+    Bool,
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    U128,
+    I128,
+    F32,
+    F64,
+    String,
+    Bytes,
+    Pubkey,
+    Option(Box<IdlType>),
+    COption(Box<IdlType>),
+    Vec(Box<IdlType>),
+    Array(Box<IdlType>, usize),
+    Defined(String),
+}
+
+// An account decoded through an IDL: its type name and field values
+#[derive(Debug, Clone)]
+pub struct IdlAccount {
+    pub name: String,
+    pub value: Value,
+}
+
+impl IdlProgram {
+    #[instrument(skip(path), fields(path = %path.as_ref().display()))]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IdlError> {
+        let contents = std::fs::read_to_string(path.as_ref())?;
+        let idl: Value = serde_json::from_str(&contents)?;
+        let digest = format!("{:x}", Sha256::digest(contents.as_bytes()));
+        let program = Self::from_json(&idl, format!("idl:{}", &digest[..16]))?;
+
+        info!(
+            program = %program.name,
+            program_id = %program.program_id,
+            version = %program.version,
+            account_count = program.accounts.len(),
+            "📜 Anchor IDL loaded"
+        );
+
+        Ok(program)
+    }
+
+    pub(crate) fn from_json(idl: &Value, version: String) -> Result<Self, IdlError> {
+        let address = idl["address"]
+            .as_str()
+            .or_else(|| idl["metadata"]["address"].as_str())
+            .ok_or(IdlError::MissingAddress)?;
+        let program_id = Pubkey::from_str(address)
+            .map_err(|_| IdlError::InvalidAddress(address.to_string()))?;
+        let name = idl["metadata"]["name"]
+            .as_str()
+            .or_else(|| idl["name"].as_str())
+            .unwrap_or(address)
+            .to_string();
+
+        let mut types = HashMap::new();
+        for def in idl["types"].as_array().into_iter().flatten() {
+            let type_name = def["name"].as_str().unwrap_or_default().to_string();
+            types.insert(type_name, parse_named_type_def(def)?);
+        }
+
+        let mut accounts = Vec::new();
+        for def in idl["accounts"].as_array().into_iter().flatten() {
+            let account_name = def["name"].as_str().unwrap_or_default().to_string();
+
+            // Legacy IDLs inline the layout; 0.30+ IDLs keep it under `types`
+            let ty = if def["type"].is_object() {
+                parse_named_type_def(def)?
+            } else {
+                types
+                    .get(&account_name)
+                    .cloned()
+                    .ok_or_else(|| IdlError::UndefinedType(account_name.clone()))?
+            };
+
+            let discriminator = match def["discriminator"].as_array() {
+                Some(bytes) => {
+                    let mut discriminator = [0u8; DISCRIMINATOR_LEN];
+                    for (slot, byte) in discriminator.iter_mut().zip(bytes) {
+                        *slot = byte.as_u64().unwrap_or_default() as u8;
+                    }
+                    discriminator
+                }
+                None => account_discriminator(&account_name),
+            };
+
+            accounts.push(IdlAccountLayout {
+                name: account_name,
+                discriminator,
+                ty,
+            });
+        }
+
+        Ok(Self {
+            name,
+            program_id,
+            version,
+            accounts,
+            types,
+        })
+    }
+
+    pub fn discriminator(&self, account_name: &str) -> Option<[u8; DISCRIMINATOR_LEN]> {
+        self.accounts
+            .iter()
+            .find(|account| account.name == account_name)
+            .map(|account| account.discriminator)
+    }
+
+    // Match the discriminator and decode the fields, Borsh or zero-copy as the IDL declares;
+    // None for data that is not an IDL account
+    pub fn decode(&self, data: &[u8]) -> Option<Result<IdlAccount, IdlError>> {
+        let discriminator = data.get(..DISCRIMINATOR_LEN)?;
+        let layout = self
+            .accounts
+            .iter()
+            .find(|account| account.discriminator == discriminator)?;
+
+        let data = &data[DISCRIMINATOR_LEN..];
+        let value = match &layout.ty {
+            IdlTypeDef::ZeroCopy { fields, packed } => self.decode_zero_copy_fields(&layout.name, fields, *packed, data),
+            def => {
+                let mut reader = BorshReader {
+                    data,
+                    context: &layout.name,
+                };
+                self.decode_type_def(&layout.name, def, &mut reader)
+            }
+        };
+        Some(value.map(|value| IdlAccount {
+            name: layout.name.clone(),
+            value,
+        }))
+    }
+
+    fn decode_type_def(
+        &self,
+        name: &str,
+        def: &IdlTypeDef,
+        reader: &mut BorshReader,
+    ) -> Result<Value, IdlError> {
+        match def {
+            // Zero-copy types nested in a Borsh account are Borsh-encoded like any other struct
+            IdlTypeDef::Struct(fields) | IdlTypeDef::ZeroCopy { fields, .. } => self.decode_fields(fields, reader),
+            IdlTypeDef::Enum(variants) => {
+                let variant = reader.take(1)?[0];
+                let (variant_name, fields) = variants
+                    .get(variant as usize)
+                    .ok_or_else(|| IdlError::InvalidVariant {
+                        name: name.to_string(),
+                        variant,
+                    })?;
+
+                match fields {
+                    IdlFields::Unit => Ok(Value::String(variant_name.clone())),
+                    fields => {
+                        let mut object = Map::new();
+                        object.insert(variant_name.clone(), self.decode_fields(fields, reader)?);
+                        Ok(Value::Object(object))
+                    }
+                }
+            }
+            IdlTypeDef::Alias(ty) => self.decode_type(ty, reader),
+        }
+    }
+
+    fn decode_fields(&self, fields: &IdlFields, reader: &mut BorshReader) -> Result<Value, IdlError> {
+        match fields {
+            IdlFields::Named(fields) => {
+                let mut object = Map::new();
+                for (field_name, ty) in fields {
+                    object.insert(field_name.clone(), self.decode_type(ty, reader)?);
+                }
+                Ok(Value::Object(object))
+            }
+            IdlFields::Tuple(types) => types
+                .iter()
+                .map(|ty| self.decode_type(ty, reader))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            IdlFields::Unit => Ok(Value::Null),
+        }
+    }
+
+    fn decode_type(&self, ty: &IdlType, reader: &mut BorshReader) -> Result<Value, IdlError> {
+        // u128/i128 are emitted as strings, matching how the Carbon decoders serialize them
+        Ok(match ty {
+            IdlType::Bool => Value::Bool(reader.take(1)?[0] != 0),
+            IdlType::U8 => Value::from(reader.take(1)?[0]),
+            IdlType::I8 => Value::from(reader.take(1)?[0] as i8),
+            IdlType::U16 => Value::from(u16::from_le_bytes(reader.array()?)),
+            IdlType::I16 => Value::from(i16::from_le_bytes(reader.array()?)),
+            IdlType::U32 => Value::from(u32::from_le_bytes(reader.array()?)),
+            IdlType::I32 => Value::from(i32::from_le_bytes(reader.array()?)),
+            IdlType::U64 => Value::from(u64::from_le_bytes(reader.array()?)),
+            IdlType::I64 => Value::from(i64::from_le_bytes(reader.array()?)),
+            IdlType::U128 => Value::String(u128::from_le_bytes(reader.array()?).to_string()),
+            IdlType::I128 => Value::String(i128::from_le_bytes(reader.array()?).to_string()),
+            IdlType::F32 => float_value(f32::from_le_bytes(reader.array()?) as f64),
+            IdlType::F64 => float_value(f64::from_le_bytes(reader.array()?)),
+            IdlType::String => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                Value::String(String::from_utf8_lossy(reader.take(len)?).into_owned())
+            }
+            IdlType::Bytes => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                Value::from(reader.take(len)?.to_vec())
+            }
+            IdlType::Pubkey => Value::String(Pubkey::new_from_array(reader.array()?).to_string()),
+            IdlType::Option(inner) => match reader.take(1)?[0] {
+                0 => Value::Null,
+                _ => self.decode_type(inner, reader)?,
+            },
+            IdlType::COption(inner) => match u32::from_le_bytes(reader.array()?) {
+                0 => Value::Null,
+                _ => self.decode_type(inner, reader)?,
+            },
+            IdlType::Vec(inner) => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                // Every element takes at least one byte as far as the length goes, so a corrupt
+                // length can't spin through billions of empty elements
+                let min_element_len = self.min_encoded_len(inner, 0)?.max(1);
+                if len > reader.data.len() / min_element_len {
+                    return Err(IdlError::UnexpectedEof(reader.context.to_string()));
+                }
+                Value::Array(
+                    (0..len)
+                        .map(|_| self.decode_type(inner, reader))
+                        .collect::<Result<_, _>>()?,
+                )
+            }
+            IdlType::Array(inner, len) => Value::Array(
+                (0..*len)
+                    .map(|_| self.decode_type(inner, reader))
+                    .collect::<Result<_, _>>()?,
+            ),
+            IdlType::Defined(name) => {
+                let def = self
+                    .types
+                    .get(name)
+                    .ok_or_else(|| IdlError::UndefinedType(name.clone()))?;
+                self.decode_type_def(name, def, reader)?
+            }
+        })
+    }
+
+    // Fewest bytes a Borsh value of the type can take
+    fn min_encoded_len(&self, ty: &IdlType, depth: usize) -> Result<usize, IdlError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Ok(0);
+        }
+        Ok(match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 | IdlType::Option(_) => 1,
+            IdlType::U16 | IdlType::I16 => 2,
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => 4,
+            IdlType::String | IdlType::Bytes | IdlType::COption(_) | IdlType::Vec(_) => 4,
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => 8,
+            IdlType::U128 | IdlType::I128 => 16,
+            IdlType::Pubkey => 32,
+            IdlType::Array(inner, len) => self.min_encoded_len(inner, depth + 1)?.saturating_mul(*len),
+            IdlType::Defined(name) => {
+                let def = self
+                    .types
+                    .get(name)
+                    .ok_or_else(|| IdlError::UndefinedType(name.clone()))?;
+                match def {
+                    IdlTypeDef::Struct(fields) | IdlTypeDef::ZeroCopy { fields, .. } => {
+                        self.min_fields_len(fields, depth + 1)?
+                    }
+                    IdlTypeDef::Enum(variants) => {
+                        let mut shortest = None;
+                        for (_, fields) in variants {
+                            let len = self.min_fields_len(fields, depth + 1)?;
+                            shortest = Some(shortest.map_or(len, |shortest: usize| shortest.min(len)));
+                        }
+                        1 + shortest.unwrap_or_default()
+                    }
+                    IdlTypeDef::Alias(ty) => self.min_encoded_len(ty, depth + 1)?,
+                }
+            }
+        })
+    }
+
+    fn min_fields_len(&self, fields: &IdlFields, depth: usize) -> Result<usize, IdlError> {
+        let types: Vec<&IdlType> = match fields {
+            IdlFields::Named(fields) => fields.iter().map(|(_, ty)| ty).collect(),
+            IdlFields::Tuple(types) => types.iter().collect(),
+            IdlFields::Unit => Vec::new(),
+        };
+        types.into_iter().try_fold(0usize, |total, ty| {
+            Ok(total.saturating_add(self.min_encoded_len(ty, depth)?))
+        })
+    }
+
+    // Zero-copy structs have no length prefixes: each field sits at its repr(C) offset,
+    // padded to its alignment unless the struct is packed
+    fn decode_zero_copy_fields(
+        &self,
+        name: &str,
+        fields: &IdlFields,
+        packed: bool,
+        data: &[u8],
+    ) -> Result<Value, IdlError> {
+        let (offsets, size, _) = self.zero_copy_struct_layout(fields, packed, 0)?;
+        if data.len() < size {
+            return Err(IdlError::UnexpectedEof(name.to_string()));
+        }
+
+        match fields {
+            IdlFields::Named(fields) => {
+                let mut object = Map::new();
+                for ((field_name, ty), offset) in fields.iter().zip(offsets) {
+                    object.insert(field_name.clone(), self.decode_zero_copy(name, ty, &data[offset..])?);
+                }
+                Ok(Value::Object(object))
+            }
+            IdlFields::Tuple(types) => types
+                .iter()
+                .zip(offsets)
+                .map(|(ty, offset)| self.decode_zero_copy(name, ty, &data[offset..]))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Value::Array),
+            IdlFields::Unit => Ok(Value::Null),
+        }
+    }
+
+    // `data` starts at the value and is at least its size long
+    fn decode_zero_copy(&self, name: &str, ty: &IdlType, data: &[u8]) -> Result<Value, IdlError> {
+        match ty {
+            IdlType::Array(inner, len) => {
+                let (stride, _) = self.zero_copy_layout(inner, 0)?;
+                (0..*len)
+                    .map(|index| self.decode_zero_copy(name, inner, &data[index * stride..]))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Value::Array)
+            }
+            IdlType::Defined(type_name) => {
+                match self
+                    .types
+                    .get(type_name)
+                    .ok_or_else(|| IdlError::UndefinedType(type_name.clone()))?
+                {
+                    IdlTypeDef::ZeroCopy { fields, packed } => self.decode_zero_copy_fields(name, fields, *packed, data),
+                    IdlTypeDef::Struct(fields) => self.decode_zero_copy_fields(name, fields, false, data),
+                    IdlTypeDef::Alias(ty) => self.decode_zero_copy(name, ty, data),
+                    IdlTypeDef::Enum(_) => Err(IdlError::NotZeroCopy(type_name.clone())),
+                }
+            }
+            // Fixed-size primitives are little-endian either way
+            ty => {
+                let mut reader = BorshReader { data, context: name };
+                self.decode_type(ty, &mut reader)
+            }
+        }
+    }
+
+    // Size and alignment of a zero-copy value, as laid out for the SBF target (128-bit integers align to 8)
+    fn zero_copy_layout(&self, ty: &IdlType, depth: usize) -> Result<(usize, usize), IdlError> {
+        if depth > MAX_TYPE_DEPTH {
+            return Err(IdlError::UnsupportedType(format!("{:?}", ty)));
+        }
+        Ok(match ty {
+            IdlType::Bool | IdlType::U8 | IdlType::I8 => (1, 1),
+            IdlType::U16 | IdlType::I16 => (2, 2),
+            IdlType::U32 | IdlType::I32 | IdlType::F32 => (4, 4),
+            IdlType::U64 | IdlType::I64 | IdlType::F64 => (8, 8),
+            IdlType::U128 | IdlType::I128 => (16, 8),
+            IdlType::Pubkey => (32, 1),
+            IdlType::Array(inner, len) => {
+                let (size, align) = self.zero_copy_layout(inner, depth + 1)?;
+                let size = size
+                    .checked_mul(*len)
+                    .ok_or_else(|| IdlError::UnsupportedType(format!("{:?}", ty)))?;
+                (size, align)
+            }
+            IdlType::Defined(name) => match self
+                .types
+                .get(name)
+                .ok_or_else(|| IdlError::UndefinedType(name.clone()))?
+            {
+                IdlTypeDef::ZeroCopy { fields, packed } => {
+                    let (_, size, align) = self.zero_copy_struct_layout(fields, *packed, depth + 1)?;
+                    (size, align)
+                }
+                IdlTypeDef::Struct(fields) => {
+                    let (_, size, align) = self.zero_copy_struct_layout(fields, false, depth + 1)?;
+                    (size, align)
+                }
+                IdlTypeDef::Alias(ty) => self.zero_copy_layout(ty, depth + 1)?,
+                IdlTypeDef::Enum(_) => return Err(IdlError::NotZeroCopy(name.clone())),
+            },
+            IdlType::String | IdlType::Bytes | IdlType::Option(_) | IdlType::COption(_) | IdlType::Vec(_) => {
+                return Err(IdlError::NotZeroCopy(format!("{:?}", ty)));
+            }
+        })
+    }
+
+    // Field offsets, size and alignment of a zero-copy struct
+    fn zero_copy_struct_layout(
+        &self,
+        fields: &IdlFields,
+        packed: bool,
+        depth: usize,
+    ) -> Result<(Vec<usize>, usize, usize), IdlError> {
+        let types: Vec<&IdlType> = match fields {
+            IdlFields::Named(fields) => fields.iter().map(|(_, ty)| ty).collect(),
+            IdlFields::Tuple(types) => types.iter().collect(),
+            IdlFields::Unit => Vec::new(),
+        };
+
+        let mut offsets = Vec::with_capacity(types.len());
+        let mut offset = 0usize;
+        let mut struct_align = 1;
+        for ty in types {
+            let (size, align) = self.zero_copy_layout(ty, depth)?;
+            let align = if packed { 1 } else { align };
+            offset = offset.next_multiple_of(align);
+            offsets.push(offset);
+            offset = offset
+                .checked_add(size)
+                .ok_or_else(|| IdlError::UnsupportedType(format!("{:?}", ty)))?;
+            struct_align = struct_align.max(align);
+        }
+
+        Ok((offsets, offset.next_multiple_of(struct_align), struct_align))
+    }
+}
+
+// Carbon decoder backed by a runtime-loaded IDL
+pub struct IdlDecoder {
+    program: Arc<IdlProgram>,
+}
+
+impl IdlDecoder {
+    pub fn new(program: Arc<IdlProgram>) -> Self {
+        Self { program }
+    }
+}
+
+impl<'a> AccountDecoder<'a> for IdlDecoder {
+    type AccountType = IdlAccount;
+
+    fn decode_account(
+        &self,
+        account: &'a solana_account::Account,
+    ) -> Option<DecodedAccount<Self::AccountType>> {
+        if account.owner != self.program.program_id {
+            return None;
+        }
+
+        match self.program.decode(&account.data)? {
+            Ok(data) => Some(DecodedAccount {
+                lamports: account.lamports,
+                data,
+                owner: account.owner,
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+            }),
+            Err(e) => {
+                debug!(program = %self.program.name, error = %e, "⚠️ Failed to decode account from IDL");
+                None
+            }
+        }
+    }
+}
+
+struct BorshReader<'a> {
+    data: &'a [u8],
+    context: &'a str,
+}
+
+impl<'a> BorshReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], IdlError> {
+        if self.data.len() < len {
+            return Err(IdlError::UnexpectedEof(self.context.to_string()));
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], IdlError> {
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        Ok(bytes)
+    }
+}
+
+// Legacy IDLs omit discriminators: Anchor derives them as sha256("account:<Name>")[..8]
+pub fn account_discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let hash = Sha256::digest(format!("account:{}", name).as_bytes());
+    let mut discriminator = [0u8; DISCRIMINATOR_LEN];
+    discriminator.copy_from_slice(&hash[..DISCRIMINATOR_LEN]);
+    discriminator
+}
+
+fn float_value(value: f64) -> Value {
+    Number::from_f64(value).map(Value::Number).unwrap_or(Value::Null)
+}
+
+// A `types` or legacy `accounts` entry: the layout under `type`, zero-copy when the IDL says so
+fn parse_named_type_def(def: &Value) -> Result<IdlTypeDef, IdlError> {
+    let ty = parse_type_def(&def["type"])?;
+    let zero_copy = matches!(def["serialization"].as_str(), Some("bytemuck" | "bytemuckunsafe"));
+
+    Ok(match ty {
+        IdlTypeDef::Struct(fields) if zero_copy => IdlTypeDef::ZeroCopy {
+            fields,
+            packed: def["repr"]["packed"].as_bool().unwrap_or(false),
+        },
+        ty => ty,
+    })
+}
+
+fn parse_type_def(def: &Value) -> Result<IdlTypeDef, IdlError> {
+    match def["kind"].as_str() {
+        Some("struct") => Ok(IdlTypeDef::Struct(parse_fields(&def["fields"])?)),
+        Some("enum") => def["variants"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|variant| {
+                let variant_name = variant["name"].as_str().unwrap_or_default().to_string();
+                Ok((variant_name, parse_fields(&variant["fields"])?))
+            })
+            .collect::<Result<Vec<_>, IdlError>>()
+            .map(IdlTypeDef::Enum),
+        Some("type") | Some("alias") => Ok(IdlTypeDef::Alias(parse_type(&def["alias"])?)),
+        _ => Err(IdlError::UnsupportedType(def.to_string())),
+    }
+}
+
+fn parse_fields(fields: &Value) -> Result<IdlFields, IdlError> {
+    let Some(fields) = fields.as_array().filter(|fields| !fields.is_empty()) else {
+        return Ok(IdlFields::Unit);
+    };
+
+    // Named fields are `{ name, type }` objects, tuple fields are bare types
+    if fields[0].get("name").is_some() {
+        fields
+            .iter()
+            .map(|field| {
+                let field_name = field["name"].as_str().unwrap_or_default().to_string();
+                Ok((field_name, parse_type(&field["type"])?))
+            })
+            .collect::<Result<Vec<_>, IdlError>>()
+            .map(IdlFields::Named)
+    } else {
+        fields
+            .iter()
+            .map(parse_type)
+            .collect::<Result<Vec<_>, IdlError>>()
+            .map(IdlFields::Tuple)
+    }
+}
+
+fn parse_type(ty: &Value) -> Result<IdlType, IdlError> {
+    if let Some(primitive) = ty.as_str() {
+        return Ok(match primitive {
+            "bool" => IdlType::Bool,
+            "u8" => IdlType::U8,
+            "i8" => IdlType::I8,
+            "u16" => IdlType::U16,
+            "i16" => IdlType::I16,
+            "u32" => IdlType::U32,
+            "i32" => IdlType::I32,
+            "u64" => IdlType::U64,
+            "i64" => IdlType::I64,
+            "u128" => IdlType::U128,
+            "i128" => IdlType::I128,
+            "f32" => IdlType::F32,
+            "f64" => IdlType::F64,
+            "string" => IdlType::String,
+            "bytes" => IdlType::Bytes,
+            "pubkey" | "publicKey" => IdlType::Pubkey,
+            other => return Err(IdlError::UnsupportedType(other.to_string())),
+        });
+    }
+
+    if let Some(inner) = ty.get("option") {
+        Ok(IdlType::Option(Box::new(parse_type(inner)?)))
+    } else if let Some(inner) = ty.get("coption") {
+        Ok(IdlType::COption(Box::new(parse_type(inner)?)))
+    } else if let Some(inner) = ty.get("vec") {
+        Ok(IdlType::Vec(Box::new(parse_type(inner)?)))
+    } else if let Some(array) = ty.get("array") {
+        let len = array[1]
+            .as_u64()
+            .ok_or_else(|| IdlError::UnsupportedType(ty.to_string()))?;
+        Ok(IdlType::Array(Box::new(parse_type(&array[0])?), len as usize))
+    } else if let Some(defined) = ty.get("defined") {
+        // Legacy IDLs use `{ "defined": "Name" }`, 0.30+ uses `{ "defined": { "name": "Name" } }`
+        let name = defined
+            .as_str()
+            .or_else(|| defined["name"].as_str())
+            .ok_or_else(|| IdlError::UnsupportedType(ty.to_string()))?;
+        Ok(IdlType::Defined(name.to_string()))
+    } else {
+        Err(IdlError::UnsupportedType(ty.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const PROGRAM_ID: &str = "cpamdpZCGKUy5JxQXB4dcpGPiikHawvSWAd6mEn1sGG";
+
+    fn program(idl: Value) -> IdlProgram {
+        IdlProgram::from_json(&idl, "idl:test".to_string()).unwrap()
+    }
+
+    fn account_data(program: &IdlProgram, account: &str, fields: &[&[u8]]) -> Vec<u8> {
+        let mut data = program.discriminator(account).unwrap().to_vec();
+        for field in fields {
+            data.extend_from_slice(field);
+        }
+        data
+    }
+
+    fn borsh_program() -> IdlProgram {
+        program(json!({
+            "address": PROGRAM_ID,
+            "metadata": { "name": "vault" },
+            "accounts": [{ "name": "Vault", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }],
+            "types": [
+                {
+                    "name": "Vault",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "authority", "type": "pubkey" },
+                            { "name": "amount", "type": "u64" },
+                            { "name": "total", "type": "u128" },
+                            { "name": "label", "type": "string" },
+                            { "name": "fees", "type": { "vec": "u16" } },
+                            { "name": "delegate", "type": { "option": "pubkey" } },
+                            { "name": "markers", "type": { "vec": { "defined": { "name": "Marker" } } } },
+                            { "name": "status", "type": { "defined": { "name": "Status" } } }
+                        ]
+                    }
+                },
+                {
+                    "name": "Status",
+                    "type": {
+                        "kind": "enum",
+                        "variants": [
+                            { "name": "Active" },
+                            { "name": "Frozen", "fields": [{ "name": "until", "type": "i64" }] }
+                        ]
+                    }
+                },
+                { "name": "Marker", "type": { "kind": "struct", "fields": [] } }
+            ]
+        }))
+    }
+
+    #[test]
+    fn decodes_borsh_fields_of_a_current_idl() {
+        let program = borsh_program();
+        let authority = Pubkey::new_unique();
+        let data = account_data(&program, "Vault", &[
+            authority.as_ref(),
+            &42u64.to_le_bytes(),
+            &u128::MAX.to_le_bytes(),
+            &3u32.to_le_bytes(),
+            b"abc",
+            &2u32.to_le_bytes(),
+            &7u16.to_le_bytes(),
+            &9u16.to_le_bytes(),
+            &[0],
+            &2u32.to_le_bytes(),
+            &[1],
+            &(-5i64).to_le_bytes(),
+        ]);
+
+        let account = program.decode(&data).unwrap().unwrap();
+
+        assert_eq!(account.name, "Vault");
+        assert_eq!(
+            account.value,
+            json!({
+                "authority": authority.to_string(),
+                "amount": 42,
+                "total": u128::MAX.to_string(),
+                "label": "abc",
+                "fees": [7, 9],
+                "delegate": null,
+                "markers": [null, null],
+                "status": { "Frozen": { "until": -5 } }
+            })
+        );
+    }
+
+    #[test]
+    fn derives_discriminators_for_legacy_idls() {
+        let program = program(json!({
+            "name": "legacy",
+            "metadata": { "address": PROGRAM_ID },
+            "accounts": [{
+                "name": "Counter",
+                "type": { "kind": "struct", "fields": [{ "name": "count", "type": "u32" }] }
+            }]
+        }));
+        let data = account_data(&program, "Counter", &[&5u32.to_le_bytes()]);
+
+        assert_eq!(program.discriminator("Counter"), Some(account_discriminator("Counter")));
+        assert_eq!(program.decode(&data).unwrap().unwrap().value, json!({ "count": 5 }));
+    }
+
+    #[test]
+    fn ignores_data_with_an_unknown_discriminator() {
+        let program = borsh_program();
+
+        assert!(program.decode(&[9; 16]).is_none());
+        assert!(program.decode(&[1, 2, 3]).is_none());
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let program = borsh_program();
+        let data = account_data(&program, "Vault", &[Pubkey::new_unique().as_ref(), &42u32.to_le_bytes()]);
+
+        assert!(matches!(program.decode(&data), Some(Err(IdlError::UnexpectedEof(_)))));
+    }
+
+    #[test]
+    fn bounds_vec_lengths_by_the_remaining_bytes() {
+        let program = program(json!({
+            "address": PROGRAM_ID,
+            "accounts": [{ "name": "Markers", "discriminator": [1, 1, 1, 1, 1, 1, 1, 1] }],
+            "types": [
+                {
+                    "name": "Markers",
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "empty", "type": { "vec": { "defined": { "name": "Marker" } } } },
+                            { "name": "amounts", "type": { "vec": "u64" } }
+                        ]
+                    }
+                },
+                { "name": "Marker", "type": { "kind": "struct", "fields": [] } }
+            ]
+        }));
+
+        // A zero-size element type with a length near u32::MAX must fail instead of looping
+        let data = account_data(&program, "Markers", &[&u32::MAX.to_le_bytes(), &0u32.to_le_bytes()]);
+        assert!(matches!(program.decode(&data), Some(Err(IdlError::UnexpectedEof(_)))));
+
+        let data = account_data(&program, "Markers", &[&0u32.to_le_bytes(), &2u32.to_le_bytes(), &1u64.to_le_bytes()]);
+        assert!(matches!(program.decode(&data), Some(Err(IdlError::UnexpectedEof(_)))));
+    }
+
+    fn zero_copy_program() -> IdlProgram {
+        program(json!({
+            "address": PROGRAM_ID,
+            "accounts": [
+                { "name": "Pool", "discriminator": [2, 2, 2, 2, 2, 2, 2, 2] },
+                { "name": "Packed", "discriminator": [3, 3, 3, 3, 3, 3, 3, 3] }
+            ],
+            "types": [
+                {
+                    "name": "Pool",
+                    "serialization": "bytemuck",
+                    "repr": { "kind": "c" },
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "enabled", "type": "u8" },
+                            { "name": "liquidity", "type": "u64" },
+                            { "name": "mint", "type": "pubkey" },
+                            { "name": "rewards", "type": { "array": [{ "defined": { "name": "Reward" } }, 2] } },
+                            { "name": "bump", "type": "u8" }
+                        ]
+                    }
+                },
+                {
+                    "name": "Reward",
+                    "serialization": "bytemuck",
+                    "repr": { "kind": "c" },
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "rate", "type": "u128" },
+                            { "name": "index", "type": "u16" }
+                        ]
+                    }
+                },
+                {
+                    "name": "Packed",
+                    "serialization": "bytemuck",
+                    "repr": { "kind": "c", "packed": true },
+                    "type": {
+                        "kind": "struct",
+                        "fields": [
+                            { "name": "flag", "type": "bool" },
+                            { "name": "amount", "type": "u64" }
+                        ]
+                    }
+                }
+            ]
+        }))
+    }
+
+    #[test]
+    fn decodes_zero_copy_accounts_with_repr_c_padding() {
+        let program = zero_copy_program();
+        let mint = Pubkey::new_unique();
+        let reward = |rate: u128, index: u16| [&rate.to_le_bytes()[..], &index.to_le_bytes()[..], &[0u8; 6][..]].concat();
+        let data = account_data(&program, "Pool", &[
+            &[1],
+            &[0; 7],
+            &500u64.to_le_bytes(),
+            mint.as_ref(),
+            &reward(10, 1),
+            &reward(20, 2),
+            &[254],
+            &[0; 7],
+        ]);
+
+        let account = program.decode(&data).unwrap().unwrap();
+
+        assert_eq!(
+            account.value,
+            json!({
+                "enabled": 1,
+                "liquidity": 500,
+                "mint": mint.to_string(),
+                "rewards": [{ "rate": "10", "index": 1 }, { "rate": "20", "index": 2 }],
+                "bump": 254
+            })
+        );
+    }
+
+    #[test]
+    fn decodes_packed_zero_copy_accounts_without_padding() {
+        let program = zero_copy_program();
+        let data = account_data(&program, "Packed", &[&[1], &7u64.to_le_bytes()]);
+
+        let account = program.decode(&data).unwrap().unwrap();
+
+        assert_eq!(account.value, json!({ "flag": true, "amount": 7 }));
+    }
+
+    #[test]
+    fn rejects_zero_copy_accounts_shorter_than_their_layout() {
+        let program = zero_copy_program();
+        let data = account_data(&program, "Pool", &[&[1], &[0; 7], &500u64.to_le_bytes()]);
+
+        assert!(matches!(program.decode(&data), Some(Err(IdlError::UnexpectedEof(_)))));
+    }
+}
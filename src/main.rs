@@ -1,26 +1,45 @@
 use anyhow::Result;
-use carbon_core::pipeline::{Pipeline, ShutdownStrategy};
-
-use carbon_meteora_damm_v2_decoder::{MeteoraDammV2Decoder, PROGRAM_ID};
-use carbon_rpc_program_subscribe_datasource::{Filters, RpcProgramSubscribe};
 use dotenv::dotenv;
-use solana_account_decoder::UiAccountEncoding;
-use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use tokio::task::JoinSet;
 use tracing::{info, warn, error, debug, Level};
 use tracing_subscriber::{fmt, EnvFilter, layer::SubscriberExt, util::SubscriberInitExt};
 
+mod backfill;
+mod block_subscribe;
 mod cache;
+mod closure;
+mod commitment;
 mod config;
 mod database;
+mod fanout;
+mod filters;
+mod idl;
+mod mints;
+mod nft_subscribe;
+mod pipeline;
+mod portfolio;
+mod positions;
+mod pricing;
 mod processor;
+mod reconcile;
+mod redecode;
+mod registry;
+mod supervisor;
+#[cfg(test)]
+mod test_support;
+mod valuation;
 mod websocket;
+mod yellowstone;
 
 use crate::cache::RedisCache;
 use crate::database::Database;
-use crate::processor::{MeteoraDammV2AccountProcessor, PROCESSOR_STATE, ProcessorState};
+use crate::fanout::Fanout;
+use crate::mints::MintMetadataService;
+use crate::processor::ProcessorContext;
+use crate::valuation::Revaluations;
 use crate::websocket::WebSocketServer;
-use carbon_log_metrics::LogMetrics;
-use config::ServiceConfig;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use config::{Command, PipelineConfig, RedecodeConfig, ServiceConfig};
 use std::sync::Arc;
 
 #[tokio::main]
@@ -45,8 +64,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     info!("🚀 Starting account socket server");
 
     let config = ServiceConfig::from_env()?;
-    info!("📊 Configuration loaded");
-    debug!("RPC URL: {}", config.rpc_url);
+    info!(command = ?config.command, run_mode = ?config.run_mode, "📊 Configuration loaded");
+    for pipeline in &config.pipelines {
+        for endpoint in &pipeline.endpoints {
+            debug!("Pipeline {}: RPC URL {}", pipeline.name, endpoint.ws_url);
+        }
+    }
     debug!("WebSocket: {}:{}", config.websocket.host, config.websocket.port);
     debug!("Redis: {}", config.redis.url);
     debug!("Database: {}", config.database.url);
@@ -61,59 +84,153 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cache = Arc::new(RedisCache::new(&config.redis.url).await?);
     info!("✅ Redis connection established");
 
-    // Initialize WebSocket server
-    info!("🌐 Setting up WebSocket server");
-    let websocket_server = Arc::new(WebSocketServer::new(database.clone(), cache.clone()));
-    info!("✅ WebSocket server initialized");
+    // Ingestors publish stored updates here and gateways forward them to their clients
+    let fanout = Arc::new(Fanout::new(&config.redis.url, &config.redis.fanout_channel)?);
+    info!(channel = %config.redis.fanout_channel, "✅ Fanout channel configured");
+
+    match config.command {
+        Command::Backfill => return run_backfill(&config, database, cache, fanout).await,
+        Command::Redecode(redecode) => return run_redecode(&config, redecode, database, cache, fanout).await,
+        Command::Run => {}
+    }
+
+    let gateway_handle = if config.run_mode.runs_gateway() {
+        // Initialize WebSocket server
+        info!("🌐 Setting up WebSocket server");
+        let websocket_server = Arc::new(WebSocketServer::new(database.clone(), cache.clone()));
+        info!("✅ WebSocket server initialized");
+
+        // Forward updates published by the ingestor to this gateway's clients
+        let fanout_subscriber = fanout.clone();
+        let websocket_for_fanout = websocket_server.clone();
+        tokio::spawn(async move {
+            fanout_subscriber.run_subscriber(websocket_for_fanout).await;
+        });
+
+        // Create Warp WebSocket server using websocket module
+        let ws_route = websocket_server.clone().create_websocket_filter();
+
+        let server_addr = ([127, 0, 0, 1], config.websocket.port);
+        info!("🌐 Starting Warp WebSocket server on http://{}:{}/ws", config.websocket.host, config.websocket.port);
+
+        // Start the Warp server in background
+        Some(tokio::spawn(async move {
+            info!("🚀 WebSocket server listening on {}", server_addr.1);
+            warp::serve(ws_route)
+                .run(server_addr)
+                .await;
+        }))
+    } else {
+        None
+    };
+
+    if !config.run_mode.runs_ingest() {
+        info!("🛰️ Running as gateway only, Carbon pipeline disabled");
+        if let Some(handle) = gateway_handle {
+            handle.await?;
+        }
+        return Ok(());
+    }
+
+    // Each pipeline gets its own processor handles, so several can run side by side
+    let mut pipelines = JoinSet::new();
+    for pipeline_config in config.pipelines.clone() {
+        let context = processor_context(&pipeline_config, &database, &cache, &fanout);
+        pipelines.spawn(supervisor::supervise(pipeline_config, context));
+    }
+    info!(pipeline_count = pipelines.len(), "✅ Carbon pipelines started");
+
+    // Supervisors rebuild failed pipelines, so only a panic ends one
+    if let Some(result) = pipelines.join_next().await {
+        result?;
+    }
+
+    Ok(())
+}
 
-    // Initialize global processor state
-    let processor_state = ProcessorState {
+fn processor_context(
+    pipeline_config: &PipelineConfig,
+    database: &Arc<Database>,
+    cache: &Arc<RedisCache>,
+    fanout: &Arc<Fanout>,
+) -> ProcessorContext {
+    ProcessorContext {
+        pipeline: pipeline_config.name.clone(),
         database: database.clone(),
         cache: cache.clone(),
-        websocket_server: websocket_server.clone(),
-    };
+        fanout: fanout.clone(),
+        write_versions: Default::default(),
+        block_groups: pipeline_config
+            .datasource
+            .groups_blocks()
+            .then(Default::default),
+        last_slot: Default::default(),
+        commitment: pipeline_config.commitment,
+        dedup: pipeline_config.dedup,
+        skipped_updates: Default::default(),
+        // Mint metadata barely changes, so the first endpoint is good enough
+        mint_metadata: Arc::new(MintMetadataService::new(
+            RpcClient::new(pipeline_config.endpoints[0].http_url.clone()),
+            database.clone(),
+            cache.clone(),
+        )),
+        // Only block times are read, so the first endpoint is good enough here too
+        revaluations: Arc::new(Revaluations::new(pipeline_config.endpoints[0].http_url.clone())),
+        indexed_accounts: Default::default(),
+    }
+}
 
-    PROCESSOR_STATE.set(processor_state).expect("Failed to set processor state");
-    info!("✅ Processor state initialized");
-
-    // Create Warp WebSocket server using websocket module
-    let ws_route = websocket_server.clone().create_websocket_filter();
-
-    let server_addr = ([127, 0, 0, 1], config.websocket.port);
-    info!("🌐 Starting Warp WebSocket server on http://{}:{}/ws", config.websocket.host, config.websocket.port);
-
-    // Start the Warp server in background
-    tokio::spawn(async move {
-        info!("🚀 WebSocket server listening on {}", server_addr.1);
-        warp::serve(ws_route)
-            .run(server_addr)
-            .await;
-    });
-
-    info!("⚙️  Building Carbon pipeline");
-    let mut pipeline = Pipeline::builder()
-        .datasource(RpcProgramSubscribe::new(
-            config.rpc_url.clone(),
-            Filters::new(
-                PROGRAM_ID,
-                Some(RpcProgramAccountsConfig {
-                    filters: None,
-                    account_config: RpcAccountInfoConfig {
-                        encoding: Some(UiAccountEncoding::Base64),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                }),
-            ),
-        ))
-        .account(MeteoraDammV2Decoder, MeteoraDammV2AccountProcessor)
-        .metrics(Arc::new(LogMetrics::new()))
-        .shutdown_strategy(ShutdownStrategy::ProcessPending)
-        .build()?;
-
-    info!("🔥 Starting Carbon pipeline for Meteora DAMM V2 accounts");
-    info!("🎯 Target program: {}", PROGRAM_ID);
-    pipeline.run().await?;
+// `account_socket backfill`: seed every pipeline from the first endpoint that answers, then exit
+async fn run_backfill(
+    config: &ServiceConfig,
+    database: Arc<Database>,
+    cache: Arc<RedisCache>,
+    fanout: Arc<Fanout>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for pipeline_config in &config.pipelines {
+        let context = processor_context(pipeline_config, &database, &cache, &fanout);
+
+        let mut last_error = None;
+        for endpoint in &pipeline_config.endpoints {
+            match backfill::backfill_pipeline(pipeline_config, endpoint, &context).await {
+                Ok(()) => {
+                    last_error = None;
+                    break;
+                }
+                Err(e) => {
+                    warn!(pipeline = %pipeline_config.name, endpoint = %endpoint.http_url, error = %e, "⚠️ Backfill failed, trying next endpoint");
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if let Some(e) = last_error {
+            error!(pipeline = %pipeline_config.name, error = %e, "❌ Backfill failed on every endpoint");
+            return Err(e.into());
+        }
+        // No background worker runs in this mode, so value the backfilled positions before exiting
+        valuation::drain(&context).await;
+    }
+
+    info!(pipeline_count = config.pipelines.len(), "✅ Backfill finished");
+    Ok(())
+}
 
+async fn run_redecode(
+    config: &ServiceConfig,
+    redecode: RedecodeConfig,
+    database: Arc<Database>,
+    cache: Arc<RedisCache>,
+    fanout: Arc<Fanout>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for pipeline_config in &config.pipelines {
+        let context = processor_context(pipeline_config, &database, &cache, &fanout);
+        if let Err(e) = redecode::redecode_pipeline(pipeline_config, redecode, &context).await {
+            error!(pipeline = %pipeline_config.name, error = %e, "❌ Re-decode failed");
+            return Err(e.into());
+        }
+    }
+
+    info!(pipeline_count = config.pipelines.len(), "✅ Re-decode finished");
     Ok(())
 }
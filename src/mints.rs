@@ -0,0 +1,461 @@
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{info, warn, debug, instrument};
+
+use crate::cache::RedisCache;
+use crate::database::Database;
+
+// getMultipleAccounts accepts at most 100 pubkeys per call
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+pub const SPL_TOKEN_PROGRAM_ID: Pubkey = Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+const METAPLEX_METADATA_PROGRAM_ID: Pubkey = Pubkey::from_str_const("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+// Base mint layout, shared by SPL Token and Token-2022
+const MINT_SUPPLY_OFFSET: usize = 36;
+const MINT_DECIMALS_OFFSET: usize = 44;
+// Token-2022 extensions start after the account padded to a token account's size and its account type byte
+const TOKEN_2022_EXTENSIONS_OFFSET: usize = 166;
+const TOKEN_METADATA_EXTENSION: u16 = 19;
+// Metaplex metadata: key, update authority and mint precede the name
+const METAPLEX_NAME_OFFSET: usize = 65;
+
+// Supply changes, so stored metadata is refreshed after this long
+const METADATA_MAX_AGE_SECONDS: i64 = 3600;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MintMetadata {
+    pub mint: String,
+    pub decimals: u8,
+    pub supply: u64,
+    pub token_program: String, // "spl_token" or "token_2022"
+    // From the Token-2022 metadata extension, or else the Metaplex metadata account
+    pub name: Option<String>,
+    pub symbol: Option<String>,
+}
+
+// Metadata of a pool's two mints, attached to its messages
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolMints {
+    pub token_a: MintMetadata,
+    pub token_b: MintMetadata,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum MintMetadataError {
+    #[error("RPC request failed: {0}")]
+    Rpc(#[from] ClientError),
+
+    #[error("Database query failed: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+// Where mint and metadata accounts are read from: the RPC node, or a stub
+#[async_trait]
+pub trait AccountSource: Send + Sync {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<solana_account::Account>>, ClientError>;
+}
+
+#[async_trait]
+impl AccountSource for RpcClient {
+    async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<solana_account::Account>>, ClientError> {
+        let response = self
+            .get_multiple_accounts_with_config(
+                pubkeys,
+                RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        Ok(response.value)
+    }
+}
+
+// Resolves mint metadata through Redis, then SQLite, then batched getMultipleAccounts calls
+pub struct MintMetadataService {
+    source: Box<dyn AccountSource>,
+    database: Arc<Database>,
+    cache: Arc<RedisCache>,
+}
+
+impl std::fmt::Debug for MintMetadataService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MintMetadataService").finish_non_exhaustive()
+    }
+}
+
+impl MintMetadataService {
+    pub fn new(source: impl AccountSource + 'static, database: Arc<Database>, cache: Arc<RedisCache>) -> Self {
+        Self {
+            source: Box::new(source),
+            database,
+            cache,
+        }
+    }
+
+    // Metadata of each mint, in order; None for accounts that are missing or not mints
+    #[instrument(skip(self, mints), fields(mint_count = mints.len()))]
+    pub async fn get(&self, mints: &[Pubkey]) -> Result<Vec<Option<MintMetadata>>, MintMetadataError> {
+        let keys: Vec<String> = mints.iter().map(Pubkey::to_string).collect();
+        let mut resolved: HashMap<String, MintMetadata> = HashMap::new();
+
+        match self.cache.get_mints(&keys).await {
+            Ok(cached) => resolved.extend(cached.into_iter().flatten().map(|metadata| (metadata.mint.clone(), metadata))),
+            Err(e) => warn!(error = %e, "⚠️ Failed to read mint metadata from Redis"),
+        }
+
+        let missing: Vec<String> = keys.iter().filter(|key| !resolved.contains_key(*key)).cloned().collect();
+        if !missing.is_empty() {
+            let fresh_since = Utc::now() - Duration::seconds(METADATA_MAX_AGE_SECONDS);
+            for metadata in self.database.get_mint_metadata(&missing, fresh_since).await? {
+                self.cache_mint(&metadata).await;
+                resolved.insert(metadata.mint.clone(), metadata);
+            }
+        }
+
+        let missing: Vec<Pubkey> = mints
+            .iter()
+            .filter(|mint| !resolved.contains_key(&mint.to_string()))
+            .copied()
+            .collect();
+        if !missing.is_empty() {
+            for metadata in fetch(self.source.as_ref(), &missing).await? {
+                self.database.upsert_mint_metadata(&metadata).await?;
+                self.cache_mint(&metadata).await;
+                resolved.insert(metadata.mint.clone(), metadata);
+            }
+        }
+
+        Ok(keys.iter().map(|key| resolved.get(key).cloned()).collect())
+    }
+
+    // Both mints of a pool, or None unless both resolve
+    pub async fn pool_mints(&self, token_a_mint: Pubkey, token_b_mint: Pubkey) -> Result<Option<PoolMints>, MintMetadataError> {
+        let mut metadata = self.get(&[token_a_mint, token_b_mint]).await?.into_iter();
+        Ok(match (metadata.next().flatten(), metadata.next().flatten()) {
+            (Some(token_a), Some(token_b)) => Some(PoolMints { token_a, token_b }),
+            _ => None,
+        })
+    }
+
+    async fn cache_mint(&self, metadata: &MintMetadata) {
+        if let Err(e) = self.cache.set_mint(metadata).await {
+            warn!(mint = %metadata.mint, error = %e, "⚠️ Failed to cache mint metadata in Redis");
+        }
+    }
+}
+
+// Mint accounts first, then Metaplex metadata for the mints without a Token-2022 metadata extension
+async fn fetch(source: &dyn AccountSource, mints: &[Pubkey]) -> Result<Vec<MintMetadata>, ClientError> {
+    let mut fetched: Vec<(Pubkey, MintMetadata)> = Vec::new();
+    for (mint, account) in mints.iter().zip(get_accounts(source, mints).await?) {
+        match account.and_then(|account| parse_mint(mint, &account)) {
+            Some(metadata) => fetched.push((*mint, metadata)),
+            None => debug!(mint = %mint, "🔍 Not a token mint, skipping metadata"),
+        }
+    }
+
+    let unnamed: Vec<usize> = (0..fetched.len()).filter(|&index| fetched[index].1.name.is_none()).collect();
+    let metadata_addresses: Vec<Pubkey> = unnamed
+        .iter()
+        .map(|&index| metaplex_metadata_address(&fetched[index].0))
+        .collect();
+    for (&index, account) in unnamed.iter().zip(get_accounts(source, &metadata_addresses).await?) {
+        if let Some((name, symbol)) = account.and_then(|account| parse_metaplex_metadata(&account.data)) {
+            fetched[index].1.name = Some(name);
+            fetched[index].1.symbol = Some(symbol);
+        }
+    }
+
+    info!(requested = mints.len(), resolved = fetched.len(), "🪙 Mint metadata fetched");
+    Ok(fetched.into_iter().map(|(_, metadata)| metadata).collect())
+}
+
+async fn get_accounts(source: &dyn AccountSource, pubkeys: &[Pubkey]) -> Result<Vec<Option<solana_account::Account>>, ClientError> {
+    let mut accounts = Vec::with_capacity(pubkeys.len());
+    for chunk in pubkeys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        accounts.extend(source.get_multiple_accounts(chunk).await?);
+    }
+    Ok(accounts)
+}
+
+fn parse_mint(mint: &Pubkey, account: &solana_account::Account) -> Option<MintMetadata> {
+    let token_program = if account.owner == SPL_TOKEN_PROGRAM_ID {
+        "spl_token"
+    } else if account.owner == TOKEN_2022_PROGRAM_ID {
+        "token_2022"
+    } else {
+        return None;
+    };
+
+    let supply = u64::from_le_bytes(account.data.get(MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8)?.try_into().ok()?);
+    let decimals = *account.data.get(MINT_DECIMALS_OFFSET)?;
+    let (name, symbol) = if account.owner == TOKEN_2022_PROGRAM_ID {
+        token_2022_metadata(&account.data).unzip()
+    } else {
+        (None, None)
+    };
+
+    Some(MintMetadata {
+        mint: mint.to_string(),
+        decimals,
+        supply,
+        token_program: token_program.to_string(),
+        name,
+        symbol,
+    })
+}
+
+// Name and symbol from the TokenMetadata TLV entry: update authority and mint, then borsh strings
+fn token_2022_metadata(data: &[u8]) -> Option<(String, String)> {
+    let mut extensions = data.get(TOKEN_2022_EXTENSIONS_OFFSET..)?;
+    while extensions.len() >= 4 {
+        let extension_type = u16::from_le_bytes([extensions[0], extensions[1]]);
+        let length = u16::from_le_bytes([extensions[2], extensions[3]]) as usize;
+        let value = extensions.get(4..4 + length)?;
+        if extension_type == TOKEN_METADATA_EXTENSION {
+            let mut reader = value.get(64..)?;
+            return Some((read_string(&mut reader)?, read_string(&mut reader)?));
+        }
+        extensions = &extensions[4 + length..];
+    }
+    None
+}
+
+fn parse_metaplex_metadata(data: &[u8]) -> Option<(String, String)> {
+    let mut reader = data.get(METAPLEX_NAME_OFFSET..)?;
+    Some((read_string(&mut reader)?, read_string(&mut reader)?))
+}
+
+fn metaplex_metadata_address(mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"metadata", METAPLEX_METADATA_PROGRAM_ID.as_ref(), mint.as_ref()],
+        &METAPLEX_METADATA_PROGRAM_ID,
+    )
+    .0
+}
+
+// Borsh string; Metaplex pads names and symbols with NULs
+fn read_string(reader: &mut &[u8]) -> Option<String> {
+    let length = u32::from_le_bytes(reader.get(..4)?.try_into().ok()?) as usize;
+    let bytes = reader.get(4..4 + length)?;
+    *reader = &reader[4 + length..];
+    Some(String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    // Serves accounts from a map and records every batch it was asked for
+    #[derive(Default)]
+    struct StubSource {
+        accounts: HashMap<Pubkey, solana_account::Account>,
+        requests: Mutex<Vec<Vec<Pubkey>>>,
+    }
+
+    #[async_trait]
+    impl AccountSource for StubSource {
+        async fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> Result<Vec<Option<solana_account::Account>>, ClientError> {
+            self.requests.lock().unwrap().push(pubkeys.to_vec());
+            Ok(pubkeys.iter().map(|pubkey| self.accounts.get(pubkey).cloned()).collect())
+        }
+    }
+
+    fn borsh_string(value: &str, padded_to: usize) -> Vec<u8> {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.resize(padded_to.max(bytes.len()), 0);
+        let mut encoded = (bytes.len() as u32).to_le_bytes().to_vec();
+        encoded.extend(bytes);
+        encoded
+    }
+
+    fn mint_account(owner: Pubkey, supply: u64, decimals: u8, extensions: &[(u16, Vec<u8>)]) -> solana_account::Account {
+        let mut data = vec![0u8; 82];
+        data[MINT_SUPPLY_OFFSET..MINT_SUPPLY_OFFSET + 8].copy_from_slice(&supply.to_le_bytes());
+        data[MINT_DECIMALS_OFFSET] = decimals;
+        if !extensions.is_empty() {
+            data.resize(TOKEN_2022_EXTENSIONS_OFFSET - 1, 0);
+            data.push(1); // account type: mint
+            for (extension_type, value) in extensions {
+                data.extend(extension_type.to_le_bytes());
+                data.extend((value.len() as u16).to_le_bytes());
+                data.extend(value);
+            }
+        }
+        solana_account::Account {
+            lamports: 1_461_600,
+            data,
+            owner,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    fn token_metadata_extension(name: &str, symbol: &str) -> (u16, Vec<u8>) {
+        let mut value = vec![7u8; 64]; // update authority and mint
+        value.extend(borsh_string(name, 0));
+        value.extend(borsh_string(symbol, 0));
+        value.extend(borsh_string("https://example.com/token.json", 0));
+        (TOKEN_METADATA_EXTENSION, value)
+    }
+
+    fn metaplex_account(name: &str, symbol: &str) -> solana_account::Account {
+        let mut data = vec![4u8]; // key: MetadataV1
+        data.extend([1u8; 64]); // update authority and mint
+        data.extend(borsh_string(name, 32));
+        data.extend(borsh_string(symbol, 10));
+        data.extend(borsh_string("https://example.com/token.json", 200));
+        solana_account::Account {
+            lamports: 5_616_720,
+            data,
+            owner: METAPLEX_METADATA_PROGRAM_ID,
+            executable: false,
+            rent_epoch: u64::MAX,
+        }
+    }
+
+    #[test]
+    fn parses_spl_token_mint() {
+        let mint = Pubkey::new_unique();
+        let metadata = parse_mint(&mint, &mint_account(SPL_TOKEN_PROGRAM_ID, 1_000_000_000, 9, &[])).unwrap();
+
+        assert_eq!(
+            metadata,
+            MintMetadata {
+                mint: mint.to_string(),
+                decimals: 9,
+                supply: 1_000_000_000,
+                token_program: "spl_token".to_string(),
+                name: None,
+                symbol: None,
+            }
+        );
+    }
+
+    #[test]
+    fn parses_token_2022_metadata_after_other_extensions() {
+        let mint = Pubkey::new_unique();
+        let account = mint_account(
+            TOKEN_2022_PROGRAM_ID,
+            42,
+            6,
+            &[(1, vec![0u8; 108]), token_metadata_extension("PayPal USD", "PYUSD")],
+        );
+        let metadata = parse_mint(&mint, &account).unwrap();
+
+        assert_eq!(metadata.token_program, "token_2022");
+        assert_eq!(metadata.supply, 42);
+        assert_eq!(metadata.decimals, 6);
+        assert_eq!(metadata.name.as_deref(), Some("PayPal USD"));
+        assert_eq!(metadata.symbol.as_deref(), Some("PYUSD"));
+    }
+
+    #[test]
+    fn token_2022_mint_without_metadata_extension_has_no_name() {
+        let account = mint_account(TOKEN_2022_PROGRAM_ID, 1, 0, &[(1, vec![0u8; 108])]);
+        let metadata = parse_mint(&Pubkey::new_unique(), &account).unwrap();
+
+        assert_eq!(metadata.name, None);
+        assert_eq!(metadata.symbol, None);
+    }
+
+    #[test]
+    fn truncated_tlv_entry_is_ignored() {
+        let mut account = mint_account(TOKEN_2022_PROGRAM_ID, 1, 0, &[token_metadata_extension("Name", "SYM")]);
+        account.data.truncate(account.data.len() - 20);
+
+        assert_eq!(token_2022_metadata(&account.data), None);
+        assert_eq!(parse_mint(&Pubkey::new_unique(), &account).unwrap().name, None);
+    }
+
+    #[test]
+    fn rejects_accounts_not_owned_by_a_token_program() {
+        let account = mint_account(Pubkey::new_unique(), 1, 0, &[]);
+        assert_eq!(parse_mint(&Pubkey::new_unique(), &account), None);
+    }
+
+    #[test]
+    fn rejects_short_mint_data() {
+        let mut account = mint_account(SPL_TOKEN_PROGRAM_ID, 1, 0, &[]);
+        account.data.truncate(MINT_DECIMALS_OFFSET);
+        assert_eq!(parse_mint(&Pubkey::new_unique(), &account), None);
+    }
+
+    #[test]
+    fn parses_nul_padded_metaplex_metadata() {
+        let account = metaplex_account("Wrapped SOL", "SOL");
+        assert_eq!(
+            parse_metaplex_metadata(&account.data),
+            Some(("Wrapped SOL".to_string(), "SOL".to_string()))
+        );
+    }
+
+    #[test]
+    fn rejects_metaplex_string_longer_than_the_data() {
+        let mut data = vec![4u8; METAPLEX_NAME_OFFSET];
+        data.extend(u32::MAX.to_le_bytes());
+        data.extend(b"short");
+        assert_eq!(parse_metaplex_metadata(&data), None);
+    }
+
+    #[tokio::test]
+    async fn fetch_names_spl_mints_from_metaplex_only() {
+        let spl_mint = Pubkey::new_unique();
+        let token_2022_mint = Pubkey::new_unique();
+        let missing_mint = Pubkey::new_unique();
+        let source = StubSource {
+            accounts: HashMap::from([
+                (spl_mint, mint_account(SPL_TOKEN_PROGRAM_ID, 500, 9, &[])),
+                (metaplex_metadata_address(&spl_mint), metaplex_account("Wrapped SOL", "SOL")),
+                (
+                    token_2022_mint,
+                    mint_account(TOKEN_2022_PROGRAM_ID, 7, 6, &[token_metadata_extension("PayPal USD", "PYUSD")]),
+                ),
+            ]),
+            ..Default::default()
+        };
+
+        let fetched = fetch(&source, &[spl_mint, token_2022_mint, missing_mint]).await.unwrap();
+
+        assert_eq!(fetched.len(), 2);
+        assert_eq!(fetched[0].mint, spl_mint.to_string());
+        assert_eq!(fetched[0].name.as_deref(), Some("Wrapped SOL"));
+        assert_eq!(fetched[1].mint, token_2022_mint.to_string());
+        assert_eq!(fetched[1].symbol.as_deref(), Some("PYUSD"));
+
+        // The Token-2022 mint is named by its extension, so only the SPL mint's metadata account is read
+        let requests = source.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1], vec![metaplex_metadata_address(&spl_mint)]);
+    }
+
+    #[tokio::test]
+    async fn fetch_batches_requests_by_getmultipleaccounts_limit() {
+        let mints: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+        let source = StubSource {
+            accounts: mints
+                .iter()
+                .map(|mint| (*mint, mint_account(SPL_TOKEN_PROGRAM_ID, 1, 0, &[])))
+                .collect(),
+            ..Default::default()
+        };
+
+        let fetched = fetch(&source, &mints).await.unwrap();
+
+        assert_eq!(fetched.len(), 250);
+        let sizes: Vec<usize> = source.requests.lock().unwrap().iter().map(Vec::len).collect();
+        assert_eq!(sizes, vec![100, 100, 50, 100, 100, 50]);
+    }
+}
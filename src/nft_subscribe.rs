@@ -0,0 +1,231 @@
+use async_trait::async_trait;
+use carbon_core::datasource::{AccountUpdate, Datasource, DatasourceId, Update, UpdateType};
+use carbon_core::error::CarbonResult;
+use carbon_core::metrics::MetricsCollection;
+use futures_util::StreamExt;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::nonblocking::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_pubkey::Pubkey;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn, debug, instrument};
+
+use crate::commitment::Commitment;
+use crate::database::Database;
+use crate::mints::TOKEN_2022_PROGRAM_ID;
+use crate::portfolio::TOKEN_ACCOUNT_MINT_OFFSET;
+
+// How often the subscriptions are matched against the NFTs of open positions
+const SUBSCRIPTION_REFRESH: Duration = Duration::from_secs(10);
+
+// Wait before reconnecting after the websocket dropped
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Streams the Token-2022 accounts holding Meteora DAMM V2 position NFTs: one programSubscribe per NFT,
+// filtered with a memcmp on the mint, over a single websocket. NFTs are picked up from the database
+// as positions open and dropped once they close.
+pub struct PositionNftDatasource {
+    rpc_ws_url: String,
+    commitment: Commitment,
+    database: Arc<Database>,
+}
+
+struct Subscription {
+    cancellation_token: CancellationToken,
+    task: JoinHandle<()>,
+}
+
+impl PositionNftDatasource {
+    pub fn new(rpc_ws_url: String, commitment: Commitment, database: Arc<Database>) -> Self {
+        Self {
+            rpc_ws_url,
+            commitment,
+            database,
+        }
+    }
+
+    // Follows every tracked NFT until cancelled (Ok) or the websocket drops (Err)
+    async fn follow(
+        &self,
+        id: &DatasourceId,
+        sender: &Sender<(Update, DatasourceId)>,
+        cancellation_token: &CancellationToken,
+    ) -> Result<(), String> {
+        let client = Arc::new(PubsubClient::new(&self.rpc_ws_url).await.map_err(|e| e.to_string())?);
+        let mut subscriptions: HashMap<String, Subscription> = HashMap::new();
+        let mut interval = tokio::time::interval(SUBSCRIPTION_REFRESH);
+
+        let result = loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => break Ok(()),
+                _ = interval.tick() => {}
+            }
+
+            // Every stream ends together when the websocket drops
+            if let Some(nft_mint) = subscriptions
+                .iter()
+                .find(|(_, subscription)| subscription.task.is_finished())
+                .map(|(nft_mint, _)| nft_mint.clone())
+            {
+                break Err(format!("subscription for {} closed", nft_mint));
+            }
+
+            let nft_mints = match self.database.get_position_nft_mints().await {
+                Ok(nft_mints) => nft_mints,
+                Err(e) => {
+                    warn!(error = %e, "⚠️ Failed to load position NFTs to subscribe");
+                    continue;
+                }
+            };
+
+            // Closed positions no longer need their NFT followed
+            subscriptions.retain(|nft_mint, subscription| {
+                let open = nft_mints.binary_search(nft_mint).is_ok();
+                if !open {
+                    subscription.cancellation_token.cancel();
+                }
+                open
+            });
+
+            let mut added = 0;
+            for nft_mint in nft_mints {
+                if subscriptions.contains_key(&nft_mint) {
+                    continue;
+                }
+                let Ok(mint) = Pubkey::from_str(&nft_mint) else {
+                    continue;
+                };
+
+                let subscription_token = cancellation_token.child_token();
+                let task = tokio::spawn(follow_nft(
+                    client.clone(),
+                    mint,
+                    self.commitment,
+                    sender.clone(),
+                    id.clone(),
+                    subscription_token.clone(),
+                ));
+                subscriptions.insert(
+                    nft_mint,
+                    Subscription {
+                        cancellation_token: subscription_token,
+                        task,
+                    },
+                );
+                added += 1;
+            }
+            if added > 0 {
+                debug!(added, subscribed = subscriptions.len(), "🎫 Subscribed to position NFT token accounts");
+            }
+        };
+
+        for subscription in subscriptions.into_values() {
+            subscription.cancellation_token.cancel();
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl Datasource for PositionNftDatasource {
+    #[instrument(skip_all)]
+    async fn consume(
+        &self,
+        id: DatasourceId,
+        sender: Sender<(Update, DatasourceId)>,
+        cancellation_token: CancellationToken,
+        _metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        info!("🎫 Following position NFT token accounts");
+
+        loop {
+            match self.follow(&id, &sender, &cancellation_token).await {
+                Ok(()) => {
+                    info!("🛑 Position NFT datasource cancelled");
+                    return Ok(());
+                }
+                Err(e) => warn!(error = %e, "⚠️ Position NFT subscriptions dropped, reconnecting"),
+            }
+
+            // Transfers while disconnected are left to the holder poll
+            if let Err(e) = self.database.reset_position_nft_checks().await {
+                warn!(error = %e, "⚠️ Failed to queue position NFT holder checks");
+            }
+
+            tokio::select! {
+                _ = cancellation_token.cancelled() => return Ok(()),
+                _ = tokio::time::sleep(RECONNECT_DELAY) => {}
+            }
+        }
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::AccountUpdate]
+    }
+}
+
+// Forwards every change to a Token-2022 account of the NFT's mint until cancelled or the stream ends
+async fn follow_nft(
+    client: Arc<PubsubClient>,
+    mint: Pubkey,
+    commitment: Commitment,
+    sender: Sender<(Update, DatasourceId)>,
+    id: DatasourceId,
+    cancellation_token: CancellationToken,
+) {
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            TOKEN_ACCOUNT_MINT_OFFSET,
+            mint.to_bytes().to_vec(),
+        ))]),
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            commitment: Some(commitment.rpc_config()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let (mut accounts, unsubscribe) = match client.program_subscribe(&TOKEN_2022_PROGRAM_ID, Some(config)).await {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            warn!(nft_mint = %mint, error = %e, "⚠️ Failed to subscribe to position NFT token accounts");
+            return;
+        }
+    };
+
+    loop {
+        let response = tokio::select! {
+            _ = cancellation_token.cancelled() => {
+                unsubscribe().await;
+                return;
+            }
+            response = accounts.next() => response,
+        };
+        let Some(response) = response else {
+            return;
+        };
+
+        let Ok(pubkey) = Pubkey::from_str(&response.value.pubkey) else {
+            continue;
+        };
+        let Some(account) = response.value.account.decode::<solana_account::Account>() else {
+            continue;
+        };
+        let update = Update::Account(AccountUpdate {
+            pubkey,
+            account,
+            slot: response.context.slot,
+            transaction_signature: None,
+        });
+        if sender.send((update, id.clone())).await.is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use carbon_core::error::CarbonResult;
+use carbon_core::pipeline::{Pipeline, ShutdownStrategy};
+use carbon_log_metrics::LogMetrics;
+use carbon_rpc_block_subscribe_datasource::{Filters as BlockFilters, RpcBlockSubscribe};
+use carbon_rpc_program_subscribe_datasource::{Filters, RpcProgramSubscribe};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcBlockSubscribeConfig, RpcBlockSubscribeFilter, RpcProgramAccountsConfig,
+};
+use solana_transaction_status::{TransactionDetails, UiTransactionEncoding};
+use std::sync::Arc;
+use tracing::{info, instrument, warn};
+
+use crate::config::{DatasourceConfig, PipelineConfig, RpcEndpoint};
+use crate::block_subscribe::BlockSubscribeDatasource;
+use crate::nft_subscribe::PositionNftDatasource;
+use crate::portfolio::{TokenHoldingDecoder, TokenHoldingProcessor};
+use crate::processor::{BlockProcessor, ProcessorContext};
+use crate::supervisor;
+use crate::yellowstone::YellowstoneGrpcDatasource;
+
+#[instrument(skip(config, endpoint, context), fields(pipeline = %config.name, endpoint = %endpoint.ws_url))]
+pub fn build_pipeline(
+    config: &PipelineConfig,
+    endpoint: &RpcEndpoint,
+    context: ProcessorContext,
+) -> CarbonResult<Pipeline> {
+    info!(pipeline = %config.name, programs = ?config.programs, datasource = ?config.datasource, "⚙️  Building Carbon pipeline");
+
+    let mut builder = Pipeline::builder();
+
+    // A single Geyser stream covers every program owner
+    if let DatasourceConfig::Yellowstone(yellowstone) = &config.datasource {
+        builder = builder.datasource(YellowstoneGrpcDatasource::new(
+            yellowstone.clone(),
+            config
+                .programs
+                .iter()
+                .map(|program| (program.program_id(), config.filters.filter_sets(program)))
+                .collect(),
+            config.commitment.geyser_level(),
+            context.write_versions.clone(),
+        ));
+    }
+
+    // Every program gets its registered decoder, plus its own subscription and filters over RPC
+    for program in &config.programs {
+        match config.datasource {
+            DatasourceConfig::RpcProgramSubscribe => {
+                for filter_set in config.filters.filter_sets(program) {
+                    builder = builder.datasource(RpcProgramSubscribe::new(
+                        endpoint.ws_url.clone(),
+                        Filters::new(
+                            program.program_id(),
+                            Some(RpcProgramAccountsConfig {
+                                filters: filter_set.to_rpc_filters(),
+                                account_config: RpcAccountInfoConfig {
+                                    encoding: Some(UiAccountEncoding::Base64),
+                                    commitment: Some(config.commitment.rpc_config()),
+                                    ..Default::default()
+                                },
+                                ..Default::default()
+                            }),
+                        ),
+                    ));
+                }
+            }
+            DatasourceConfig::BlockSubscribe => {
+                builder = builder.datasource(BlockSubscribeDatasource::new(
+                    endpoint.ws_url.clone(),
+                    endpoint.http_url.clone(),
+                    program.program_id(),
+                    config.filters.filter_sets(program),
+                    config.commitment,
+                    context.block_groups.clone().unwrap_or_default(),
+                ));
+            }
+            DatasourceConfig::Yellowstone(_) => {}
+        }
+        builder = program.add_to_pipeline(builder, context.clone());
+
+        if !config.index_instructions {
+            continue;
+        }
+        if !program.indexes_instructions() {
+            warn!(pipeline = %config.name, program = program.name(), "⚠️ Instruction indexing is not supported for this program");
+            continue;
+        }
+
+        // Instructions come from blocks mentioning the program, which block mode already consumes
+        if !config.datasource.groups_blocks() {
+            builder = builder.datasource(RpcBlockSubscribe::new(
+                endpoint.ws_url.clone(),
+                BlockFilters::new(
+                    RpcBlockSubscribeFilter::MentionsAccountOrProgram(program.program_id().to_string()),
+                    Some(RpcBlockSubscribeConfig {
+                        commitment: Some(config.commitment.for_blocks().rpc_config()),
+                        encoding: Some(UiTransactionEncoding::Base64),
+                        transaction_details: Some(TransactionDetails::Full),
+                        show_rewards: Some(false),
+                        max_supported_transaction_version: Some(0),
+                        ..Default::default()
+                    }),
+                ),
+            ));
+        }
+        builder = program.add_instructions_to_pipeline(builder, context.clone());
+    }
+
+    // Wallets holding position NFTs are followed through the NFTs' token accounts
+    if supervisor::indexes_positions(config) {
+        builder = builder
+            .datasource(PositionNftDatasource::new(
+                endpoint.ws_url.clone(),
+                config.commitment,
+                context.database.clone(),
+            ))
+            .account(TokenHoldingDecoder, TokenHoldingProcessor::new(context.clone()));
+    }
+
+    if config.datasource.groups_blocks() {
+        builder = builder.block_details(BlockProcessor::new(context.clone()));
+    }
+
+    builder
+        .metrics(Arc::new(LogMetrics::new()))
+        .shutdown_strategy(ShutdownStrategy::ProcessPending)
+        .build()
+}
+
+pub async fn run_pipeline(
+    config: &PipelineConfig,
+    endpoint: &RpcEndpoint,
+    context: ProcessorContext,
+) -> CarbonResult<()> {
+    let mut pipeline = build_pipeline(config, endpoint, context)?;
+
+    info!(pipeline = %config.name, endpoint = %endpoint.ws_url, programs = ?config.programs, "🔥 Starting Carbon pipeline");
+    for program in &config.programs {
+        info!(pipeline = %config.name, "🎯 Target program: {} ({})", program.program_id(), program.name());
+    }
+    pipeline.run().await
+}
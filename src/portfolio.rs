@@ -0,0 +1,415 @@
+use async_trait::async_trait;
+use carbon_core::account::{AccountDecoder, AccountProcessorInputType, DecodedAccount};
+use carbon_core::error::Error;
+use carbon_core::metrics::MetricsCollection;
+use carbon_core::processor::Processor;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_pubkey::Pubkey;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn, debug, instrument};
+
+use crate::config::RpcEndpoint;
+use crate::database::{AccountUpdate, PositionNft};
+use crate::fanout::FanoutEvent;
+use crate::mints::{SPL_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID};
+use crate::processor::ProcessorContext;
+
+// How often the tracker checks a batch of position NFTs; new NFTs are resolved on the next poll
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+// Transfers arrive through the token account subscriptions; a held NFT's token account is only
+// re-read this long after it was last confirmed, to reconcile anything they missed
+const RECHECK_INTERVAL_SECONDS: i64 = 3600;
+
+// NFTs checked per poll, read with batched getMultipleAccounts calls
+const MAX_CHECKS_PER_POLL: i64 = 500;
+
+// getMultipleAccounts accepts at most 100 pubkeys per call
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+// NFTs that left their known token account cost a getTokenLargestAccounts call each, so they are spread over polls
+const MAX_LOOKUPS_PER_POLL: usize = 100;
+
+// Meteora DAMM V2 mints each position NFT into a token account at this PDA of the NFT mint
+const POSITION_NFT_ACCOUNT_SEED: &[u8] = b"position_nft_account";
+
+// Token account layout, shared by SPL Token and Token-2022
+pub const TOKEN_ACCOUNT_MINT_OFFSET: usize = 0;
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+const TOKEN_ACCOUNT_AMOUNT_OFFSET: usize = 64;
+
+// A position entering or leaving a wallet, through its NFT moving or the position being closed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletPositionEvent {
+    pub wallet: String,
+    pub position: String,
+    pub pool: String,
+    pub nft_mint: String,
+    pub change: String, // "added", "removed" or "closed"
+    pub slot: i64,
+    // Latest stored position state when added, the tombstone when closed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<AccountUpdate>,
+}
+
+// A token account as the position NFT subscriptions deliver it
+#[derive(Debug, Clone)]
+pub struct TokenHolding {
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+// Matches SPL Token and Token-2022 accounts, which only the position NFT datasource delivers
+pub struct TokenHoldingDecoder;
+
+impl<'a> AccountDecoder<'a> for TokenHoldingDecoder {
+    type AccountType = TokenHolding;
+
+    fn decode_account(
+        &self,
+        account: &'a solana_account::Account,
+    ) -> Option<DecodedAccount<Self::AccountType>> {
+        Some(DecodedAccount {
+            lamports: account.lamports,
+            data: token_holding(account)?,
+            owner: account.owner,
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+        })
+    }
+}
+
+// Applies holder changes streamed from the token accounts of position NFTs
+pub struct TokenHoldingProcessor {
+    context: ProcessorContext,
+}
+
+impl TokenHoldingProcessor {
+    pub fn new(context: ProcessorContext) -> Self {
+        Self { context }
+    }
+}
+
+#[async_trait]
+impl Processor for TokenHoldingProcessor {
+    type InputType = AccountProcessorInputType<TokenHolding>;
+
+    #[instrument(skip(self, input, _metrics), fields(token_account = %input.0.pubkey, slot = input.0.slot))]
+    async fn process(
+        &mut self,
+        input: Self::InputType,
+        _metrics: Arc<MetricsCollection>,
+    ) -> Result<(), Error> {
+        let (metadata, decoded, _) = input;
+
+        // The poll re-reads the holder later, so a failure here is only logged
+        if let Err(e) = observe(&self.context, &metadata.pubkey, &decoded.data, metadata.slot).await {
+            warn!(token_account = %metadata.pubkey, error = %e, "⚠️ Failed to apply position NFT token account update");
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+enum TrackerError {
+    #[error("RPC request failed: {0}")]
+    Rpc(#[from] ClientError),
+
+    #[error("Database query failed: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+// Reconciles the wallets holding Meteora DAMM V2 position NFTs; transfers themselves are streamed by
+// the pipeline's PositionNftDatasource. Each poll checks the NFTs never checked and those not checked
+// for a while: the token account known to hold each one (or, for a new NFT, the account it was minted
+// into) is read in batches, and only NFTs found elsewhere are looked up with getTokenLargestAccounts.
+// Every change of holder is published for wallet subscribers.
+#[instrument(skip(endpoint, context), fields(pipeline = %context.pipeline, endpoint = %endpoint.http_url))]
+pub async fn track(endpoint: RpcEndpoint, context: ProcessorContext) {
+    let rpc_client = RpcClient::new_with_commitment(endpoint.http_url, context.commitment.rpc_config());
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        if let Err(e) = refresh_holders(&rpc_client, &context).await {
+            warn!(error = %e, "⚠️ Position NFT tracking failed");
+        }
+    }
+}
+
+// A closed position leaves its holder's portfolio
+pub async fn publish_closed(context: &ProcessorContext, nft: PositionNft, tombstone: &AccountUpdate) {
+    let Some(wallet) = nft.owner else {
+        return;
+    };
+
+    let event = WalletPositionEvent {
+        wallet,
+        position: nft.position,
+        pool: nft.pool,
+        nft_mint: nft.nft_mint,
+        change: "closed".to_string(),
+        slot: tombstone.slot,
+        account: Some(tombstone.without_raw_data()),
+    };
+    publish(context, event).await;
+}
+
+// A token account of an NFT's mint changed: it now holds the NFT, or the known holder let go of it
+async fn observe(context: &ProcessorContext, token_account: &Pubkey, holding: &TokenHolding, slot: u64) -> Result<(), sqlx::Error> {
+    let nft_mint = holding.mint.to_string();
+    let Some(nft) = context.database.get_position_nft(&nft_mint).await? else {
+        return Ok(());
+    };
+
+    let token_account = token_account.to_string();
+    if holding.amount == 1 {
+        let owner = holding.owner.to_string();
+        if nft.token_account.as_ref() != Some(&token_account) || nft.owner.as_ref() != Some(&owner) {
+            set_holder(context, &nft, Some(token_account), Some(owner), slot).await?;
+        }
+    } else if nft.token_account.as_ref() == Some(&token_account) {
+        // Sent away; the receiving account reports the new holder, possibly before this one
+        set_holder(context, &nft, None, None, slot).await?;
+    }
+
+    context.database.mark_position_nfts_checked(&[nft_mint]).await
+}
+
+async fn refresh_holders(rpc_client: &RpcClient, context: &ProcessorContext) -> Result<(), TrackerError> {
+    let checked_before = Utc::now() - chrono::Duration::seconds(RECHECK_INTERVAL_SECONDS);
+    let nfts = context
+        .database
+        .get_position_nfts_to_check(checked_before, MAX_CHECKS_PER_POLL)
+        .await?;
+    if nfts.is_empty() {
+        return Ok(());
+    }
+
+    let mut checked = Vec::with_capacity(nfts.len());
+    let mut lookups = Vec::new();
+    for chunk in nfts.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let pubkeys: Vec<Pubkey> = chunk.iter().map(expected_token_account).collect();
+        let response = rpc_client
+            .get_multiple_accounts_with_config(
+                &pubkeys,
+                RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let slot = response.context.slot;
+
+        for ((nft, pubkey), account) in chunk.iter().zip(&pubkeys).zip(response.value) {
+            match account.and_then(|account| holder(&nft.nft_mint, &account)) {
+                Some(owner) => {
+                    let token_account = Some(pubkey.to_string());
+                    // Either unchanged, the token account handed to another wallet, or a new NFT found where it was minted
+                    if token_account != nft.token_account || nft.owner.as_ref() != Some(&owner) {
+                        set_holder(context, nft, token_account, Some(owner), slot).await?;
+                    }
+                    checked.push(nft.nft_mint.clone());
+                }
+                None => lookups.push(nft),
+            }
+        }
+    }
+
+    // NFTs beyond the cap stay unchecked, so they come first on the next poll
+    if lookups.len() > MAX_LOOKUPS_PER_POLL {
+        debug!(pending = lookups.len(), "⏳ Deferring position NFT lookups to later polls");
+    }
+    for nft in lookups.into_iter().take(MAX_LOOKUPS_PER_POLL) {
+        match find_holder(rpc_client, &nft.nft_mint).await {
+            Ok((token_account, owner, slot)) => {
+                if token_account != nft.token_account || owner != nft.owner {
+                    set_holder(context, nft, token_account, owner, slot).await?;
+                }
+            }
+            // Retried once the NFT is due again
+            Err(e) => warn!(nft_mint = %nft.nft_mint, error = %e, "⚠️ Failed to look up position NFT holder"),
+        }
+        checked.push(nft.nft_mint.clone());
+    }
+
+    context.database.mark_position_nfts_checked(&checked).await?;
+    debug!(checked = checked.len(), "🎫 Position NFT holders checked");
+    Ok(())
+}
+
+// The token account known to hold the NFT, or else the one Meteora minted it into
+fn expected_token_account(nft: &PositionNft) -> Pubkey {
+    nft.token_account
+        .as_deref()
+        .and_then(|key| Pubkey::from_str(key).ok())
+        .unwrap_or_else(|| {
+            let mint = Pubkey::from_str(&nft.nft_mint).unwrap_or_default();
+            Pubkey::find_program_address(
+                &[POSITION_NFT_ACCOUNT_SEED, mint.as_ref()],
+                &carbon_meteora_damm_v2_decoder::PROGRAM_ID,
+            )
+            .0
+        })
+}
+
+// The token account holding the NFT and its owner, or None for both while nobody holds it
+async fn find_holder(rpc_client: &RpcClient, nft_mint: &str) -> Result<(Option<String>, Option<String>, u64), ClientError> {
+    let mint = Pubkey::from_str(nft_mint).unwrap_or_default();
+    let largest = rpc_client.get_token_largest_accounts(&mint).await?;
+    let Some(address) = largest
+        .iter()
+        .find(|balance| balance.amount.amount == "1")
+        .and_then(|balance| Pubkey::from_str(&balance.address).ok())
+    else {
+        return Ok((None, None, rpc_client.get_slot().await?));
+    };
+
+    let response = rpc_client
+        .get_account_with_commitment(&address, rpc_client.commitment())
+        .await?;
+    let owner = response.value.and_then(|account| holder(nft_mint, &account));
+    Ok((owner.as_ref().map(|_| address.to_string()), owner, response.context.slot))
+}
+
+// Owner of a token account holding the NFT
+fn holder(nft_mint: &str, account: &solana_account::Account) -> Option<String> {
+    let holding = token_holding(account)?;
+    (holding.mint.to_string() == nft_mint && holding.amount == 1).then(|| holding.owner.to_string())
+}
+
+fn token_holding(account: &solana_account::Account) -> Option<TokenHolding> {
+    if account.owner != SPL_TOKEN_PROGRAM_ID && account.owner != TOKEN_2022_PROGRAM_ID {
+        return None;
+    }
+
+    let mint = Pubkey::try_from(account.data.get(TOKEN_ACCOUNT_MINT_OFFSET..TOKEN_ACCOUNT_MINT_OFFSET + 32)?).ok()?;
+    let owner = Pubkey::try_from(account.data.get(TOKEN_ACCOUNT_OWNER_OFFSET..TOKEN_ACCOUNT_OWNER_OFFSET + 32)?).ok()?;
+    let amount = u64::from_le_bytes(account.data.get(TOKEN_ACCOUNT_AMOUNT_OFFSET..TOKEN_ACCOUNT_AMOUNT_OFFSET + 8)?.try_into().ok()?);
+
+    Some(TokenHolding { mint, owner, amount })
+}
+
+// Store the new holder and tell both the wallet the NFT left and the wallet it arrived in
+async fn set_holder(
+    context: &ProcessorContext,
+    nft: &PositionNft,
+    token_account: Option<String>,
+    owner: Option<String>,
+    slot: u64,
+) -> Result<(), sqlx::Error> {
+    if !context
+        .database
+        .set_position_nft_holder(&nft.nft_mint, token_account.as_deref(), owner.as_deref(), slot)
+        .await?
+    {
+        // A holder seen at a later slot is already stored
+        return Ok(());
+    }
+    info!(
+        position = %nft.position,
+        nft_mint = %nft.nft_mint,
+        from = ?nft.owner,
+        to = ?owner,
+        slot,
+        "🔀 Position NFT changed hands"
+    );
+
+    let event = |wallet: String, change: &str, account: Option<AccountUpdate>| WalletPositionEvent {
+        wallet,
+        position: nft.position.clone(),
+        pool: nft.pool.clone(),
+        nft_mint: nft.nft_mint.clone(),
+        change: change.to_string(),
+        slot: slot as i64,
+        account,
+    };
+
+    if let Some(previous) = nft.owner.clone().filter(|previous| owner.as_ref() != Some(previous)) {
+        publish(context, event(previous, "removed", None)).await;
+    }
+    if let Some(owner) = owner.filter(|owner| nft.owner.as_ref() != Some(owner)) {
+        let account = context
+            .database
+            .get_latest_account_states(std::slice::from_ref(&nft.position))
+            .await?
+            .pop()
+            .map(|account| account.without_raw_data());
+        publish(context, event(owner, "added", account)).await;
+    }
+
+    Ok(())
+}
+
+async fn publish(context: &ProcessorContext, event: WalletPositionEvent) {
+    if let Err(e) = context.fanout.publish(&FanoutEvent::WalletPosition(event)).await {
+        warn!(error = %e, "⚠️ Failed to publish wallet position to gateways");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commitment::Commitment;
+    use crate::test_support;
+
+    fn holding(mint: Pubkey, owner: Pubkey, amount: u64) -> TokenHolding {
+        TokenHolding { mint, owner, amount }
+    }
+
+    #[tokio::test]
+    async fn follows_the_nft_across_token_accounts() {
+        let context = test_support::processor_context(Commitment::Confirmed).await;
+        let mint = Pubkey::new_unique();
+        let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (alice_account, bob_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        context
+            .database
+            .index_position_nft(&mint.to_string(), "position", "pool")
+            .await
+            .unwrap();
+
+        observe(&context, &alice_account, &holding(mint, alice, 1), 10).await.unwrap();
+        let nft = context.database.get_position_nft(&mint.to_string()).await.unwrap().unwrap();
+        assert_eq!(nft.token_account, Some(alice_account.to_string()));
+        assert_eq!(nft.owner, Some(alice.to_string()));
+
+        // The receiving account reports first, then the sending one empties
+        observe(&context, &bob_account, &holding(mint, bob, 1), 20).await.unwrap();
+        observe(&context, &alice_account, &holding(mint, alice, 0), 20).await.unwrap();
+        let nft = context.database.get_position_nft(&mint.to_string()).await.unwrap().unwrap();
+        assert_eq!(nft.token_account, Some(bob_account.to_string()));
+        assert_eq!(nft.owner, Some(bob.to_string()));
+
+        // A late notification from before the transfer doesn't hand it back
+        observe(&context, &alice_account, &holding(mint, alice, 1), 15).await.unwrap();
+        let nft = context.database.get_position_nft(&mint.to_string()).await.unwrap().unwrap();
+        assert_eq!(nft.owner, Some(bob.to_string()));
+    }
+
+    #[tokio::test]
+    async fn clears_the_holder_when_the_nft_leaves_its_account() {
+        let context = test_support::processor_context(Commitment::Confirmed).await;
+        let mint = Pubkey::new_unique();
+        let (owner, token_account) = (Pubkey::new_unique(), Pubkey::new_unique());
+        context
+            .database
+            .index_position_nft(&mint.to_string(), "position", "pool")
+            .await
+            .unwrap();
+
+        observe(&context, &token_account, &holding(mint, owner, 1), 10).await.unwrap();
+        observe(&context, &token_account, &holding(mint, owner, 0), 11).await.unwrap();
+        let nft = context.database.get_position_nft(&mint.to_string()).await.unwrap().unwrap();
+        assert_eq!(nft.token_account, None);
+        assert_eq!(nft.owner, None);
+    }
+}
@@ -0,0 +1,162 @@
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn, debug, instrument};
+
+use crate::database::AccountUpdate;
+use crate::fanout::FanoutEvent;
+use crate::portfolio;
+use crate::processor::ProcessorContext;
+use crate::valuation;
+
+// Stored positions read per query while seeding the index
+const SEED_BATCH_SIZE: i64 = 500;
+
+// A position entering, changing in or leaving a pool's position set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolPositionEvent {
+    pub pool: String,
+    pub position: String,
+    pub change: String, // "added", "changed" or "closed"
+    // The stored position update; a tombstone with a null data_json once closed
+    pub account: AccountUpdate,
+}
+
+// Keep the pool -> positions and NFT mint -> position indexes in step with a stored
+// Meteora DAMM V2 position update, and publish the membership change to gateways
+#[instrument(skip(context, account), fields(pubkey = %account.pubkey, slot = account.slot))]
+pub async fn index(context: &ProcessorContext, account: &AccountUpdate) {
+    if account.program_id != carbon_meteora_damm_v2_decoder::PROGRAM_ID.to_string() || account.account_type != "Position" {
+        return;
+    }
+
+    let indexed = if account.is_tombstone() {
+        remove(context, account).await
+    } else {
+        add(context, account).await
+    };
+
+    match indexed {
+        Ok(Some(event)) => {
+            if let Err(e) = context.fanout.publish(&FanoutEvent::PoolPosition(event)).await {
+                warn!(position = %account.pubkey, error = %e, "⚠️ Failed to publish pool position to gateways");
+            }
+        }
+        Ok(None) => {}
+        Err(e) => warn!(position = %account.pubkey, error = %e, "⚠️ Failed to index pool position"),
+    }
+}
+
+// Index every stored open position and its NFT, so positions stored before the indexes existed, or
+// before this process started, are found without waiting for them to change. Nothing is published;
+// the portfolio tracker resolves the seeded NFTs' holders.
+#[instrument(skip(context), fields(pipeline = %context.pipeline))]
+pub async fn seed(context: ProcessorContext) {
+    let program_id = carbon_meteora_damm_v2_decoder::PROGRAM_ID.to_string();
+    let mut after_id = 0;
+    let mut indexed = 0;
+    let mut nfts_indexed = 0;
+
+    loop {
+        let batch = match context
+            .database
+            .get_latest_raw_batch(&program_id, "Position", after_id, SEED_BATCH_SIZE)
+            .await
+        {
+            Ok(batch) => batch,
+            Err(e) => {
+                warn!(error = %e, "⚠️ Failed to read stored positions, pool index left partially seeded");
+                return;
+            }
+        };
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for row in batch {
+            let Some(position) = row.to_account().and_then(|raw| valuation::decode_position(&raw)) else {
+                continue;
+            };
+            let pool = position.pool.to_string();
+
+            match context
+                .database
+                .index_position_nft(&position.nft_mint.to_string(), &row.pubkey, &pool)
+                .await
+            {
+                Ok(true) => nfts_indexed += 1,
+                Ok(false) => {}
+                Err(e) => warn!(position = %row.pubkey, error = %e, "⚠️ Failed to seed position NFT"),
+            }
+
+            match context.database.index_pool_position(&row.pubkey, &pool, row.slot as u64).await {
+                Ok(true) => {
+                    indexed += 1;
+                    if let Err(e) = context.cache.add_pool_positions(&pool, std::slice::from_ref(&row.pubkey)).await {
+                        warn!(position = %row.pubkey, pool = %pool, error = %e, "⚠️ Failed to add position to Redis pool index");
+                    }
+                }
+                Ok(false) => {}
+                Err(e) => warn!(position = %row.pubkey, error = %e, "⚠️ Failed to seed pool position"),
+            }
+        }
+    }
+
+    info!(indexed, nfts_indexed, "✅ Pool position and position NFT indexes seeded from stored positions");
+}
+
+async fn add(context: &ProcessorContext, account: &AccountUpdate) -> Result<Option<PoolPositionEvent>, sqlx::Error> {
+    let Some(position) = account.to_account().and_then(|raw| valuation::decode_position(&raw)) else {
+        return Ok(None);
+    };
+    let pool = position.pool.to_string();
+
+    // The portfolio tracker resolves the NFT's holder on its next poll
+    if context
+        .database
+        .index_position_nft(&position.nft_mint.to_string(), &account.pubkey, &pool)
+        .await?
+    {
+        debug!(position = %account.pubkey, nft_mint = %position.nft_mint, "🎫 Position NFT indexed");
+    }
+
+    let added = context
+        .database
+        .index_pool_position(&account.pubkey, &pool, account.slot as u64)
+        .await?;
+    if added {
+        info!(position = %account.pubkey, pool = %pool, "➕ Position added to pool index");
+        if let Err(e) = context.cache.add_pool_positions(&pool, std::slice::from_ref(&account.pubkey)).await {
+            warn!(position = %account.pubkey, pool = %pool, error = %e, "⚠️ Failed to add position to Redis pool index");
+        }
+    }
+
+    Ok(Some(PoolPositionEvent {
+        pool,
+        position: account.pubkey.clone(),
+        change: if added { "added" } else { "changed" }.to_string(),
+        account: account.without_raw_data(),
+    }))
+}
+
+async fn remove(context: &ProcessorContext, account: &AccountUpdate) -> Result<Option<PoolPositionEvent>, sqlx::Error> {
+    if let Some(nft) = context.database.remove_position_nft(&account.pubkey).await? {
+        portfolio::publish_closed(context, nft, account).await;
+    }
+
+    let Some(pool) = context.database.remove_pool_position(&account.pubkey).await? else {
+        debug!(position = %account.pubkey, "🔍 Closed position was not indexed");
+        return Ok(None);
+    };
+
+    info!(position = %account.pubkey, pool = %pool, "➖ Position removed from pool index");
+    if let Err(e) = context.cache.remove_pool_position(&pool, &account.pubkey).await {
+        warn!(position = %account.pubkey, pool = %pool, error = %e, "⚠️ Failed to remove position from Redis pool index");
+    }
+
+    Ok(Some(PoolPositionEvent {
+        pool,
+        position: account.pubkey.clone(),
+        change: "closed".to_string(),
+        account: account.without_raw_data(),
+    }))
+}
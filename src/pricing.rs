@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use solana_pubkey::Pubkey;
+
+// sqrt_price is a Q64.64 fixed-point number
+const Q64: f64 = 18_446_744_073_709_551_616.0;
+
+// What a pool's price is computed from
+#[derive(Debug, Clone, Copy)]
+pub struct SqrtPrice {
+    pub token_a_mint: Pubkey,
+    pub token_b_mint: Pubkey,
+    pub sqrt_price: u128,
+}
+
+// Values computed from a stored account rather than decoded from it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DerivedPrice {
+    // Token B per whole token A
+    pub price_a_to_b: f64,
+    // Token A per whole token B
+    pub price_b_to_a: f64,
+}
+
+// sqrt_price squared is the price of one base unit of A in base units of B;
+// scaling by the decimals turns it into whole tokens
+pub fn spot_prices(sqrt_price: u128, decimals_a: u8, decimals_b: u8) -> DerivedPrice {
+    let sqrt = sqrt_price as f64 / Q64;
+    let price_a_to_b = sqrt * sqrt * 10f64.powi(decimals_a as i32 - decimals_b as i32);
+
+    DerivedPrice {
+        price_a_to_b,
+        price_b_to_a: if price_a_to_b > 0.0 { 1.0 / price_a_to_b } else { 0.0 },
+    }
+}
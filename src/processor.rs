@@ -1,276 +1,571 @@
 use async_trait::async_trait;
-use carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account;
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use tracing::{debug, error, info, instrument, warn};
 
 use carbon_core::{
-    account::AccountProcessorInputType, error::Error, metrics::MetricsCollection,
-    processor::Processor,
+    account::AccountProcessorInputType, datasource::BlockDetails, deserialize::ArrangeAccounts, error::Error,
+    instruction::InstructionProcessorInputType, metrics::MetricsCollection, processor::Processor,
+};
+use carbon_meteora_damm_v2_decoder::instructions::{
+    MeteoraDammV2Instruction, add_liquidity::AddLiquidity, claim_reward::ClaimReward,
+    create_position::CreatePosition, remove_liquidity::RemoveLiquidity, swap::Swap,
 };
 
+use crate::block_subscribe::BlockGroups;
+use crate::closure::IndexedAccounts;
+use crate::commitment::Commitment;
+use crate::config::DedupConfig;
 use crate::cache::RedisCache;
-use crate::database::{Database, NewAccountUpdate};
-use crate::websocket::WebSocketServer;
-
-// Global shared state for processor dependencies
-#[derive(Debug)]
-pub struct ProcessorState {
+use crate::database::{AccountUpdate, Database, NewAccountUpdate, NewInstructionEvent};
+use crate::fanout::{Fanout, FanoutEvent};
+use crate::mints::{MintMetadataService, PoolMints};
+use crate::positions;
+use crate::pricing::{self, DerivedPrice, SqrtPrice};
+use crate::registry::{IndexedProgram, NewPool, PoolListing, ProgramAccount};
+use crate::valuation::{self, Revaluations};
+use crate::yellowstone::WriteVersions;
+
+// Handles a processor needs to persist, cache and fan out account updates
+#[derive(Debug, Clone)]
+pub struct ProcessorContext {
+    pub pipeline: String,
     pub database: Arc<Database>,
     pub cache: Arc<RedisCache>,
-    pub websocket_server: Arc<WebSocketServer>,
+    pub fanout: Arc<Fanout>,
+    pub write_versions: Arc<WriteVersions>,
+    // Only set when the pipeline consumes blocks, to group stored updates per block
+    pub block_groups: Option<Arc<BlockGroups>>,
+    // Highest slot seen by any processor, used to measure gaps across reconnects
+    pub last_slot: Arc<AtomicU64>,
+    // Commitment the pipeline streams at, recorded on every stored update
+    pub commitment: Commitment,
+    pub dedup: DedupConfig,
+    // Redelivered unchanged accounts that were not stored
+    pub skipped_updates: Arc<AtomicU64>,
+    pub mint_metadata: Arc<MintMetadataService>,
+    // Pools and positions queued for the background re-valuation task
+    pub revaluations: Arc<Revaluations>,
+    // Accounts the pipeline's programs hold live state for, checked before closures touch the database
+    pub indexed_accounts: Arc<IndexedAccounts>,
 }
 
-// Thread-safe global state
-pub static PROCESSOR_STATE: tokio::sync::OnceCell<ProcessorState> =
-    tokio::sync::OnceCell::const_new();
+impl ProcessorContext {
+    pub fn record_slot(&self, slot: u64) {
+        self.last_slot.fetch_max(slot, Ordering::Relaxed);
+    }
+
+    pub fn last_slot(&self) -> u64 {
+        self.last_slot.load(Ordering::Relaxed)
+    }
+
+    // True when the account is unchanged since its latest stored version, in which case it is
+    // not stored or broadcast; a heartbeat records the slot it was seen at instead
+    async fn is_unchanged(&self, pubkey: &str, slot: u64, data_hash: &str) -> bool {
+        if !self.dedup.enabled {
+            return false;
+        }
+
+        let (id, stored_hash) = match self.database.get_latest_data_hash(pubkey).await {
+            Ok(Some(latest)) => latest,
+            Ok(None) => return false,
+            Err(e) => {
+                warn!(pubkey = %pubkey, error = %e, "⚠️ Failed to load latest content hash, storing update");
+                return false;
+            }
+        };
+        if stored_hash.as_deref() != Some(data_hash) {
+            return false;
+        }
+
+        let skipped = self.skipped_updates.fetch_add(1, Ordering::Relaxed) + 1;
+        debug!(pubkey = %pubkey, slot, skipped_total = skipped, "⏭️ Skipping unchanged account update");
+
+        if self.dedup.heartbeat {
+            if let Err(e) = self.database.record_seen_slot(id, slot).await {
+                warn!(pubkey = %pubkey, error = %e, "⚠️ Failed to record heartbeat for unchanged account");
+            }
+        }
+        true
+    }
+
+    // Spot prices and mint metadata of a pool, or None for other accounts and pools whose mints can't be read
+    pub async fn enrich_pool(&self, sqrt_price: Option<SqrtPrice>) -> Option<(DerivedPrice, PoolMints)> {
+        let pool = sqrt_price?;
+
+        match self.mint_metadata.pool_mints(pool.token_a_mint, pool.token_b_mint).await {
+            Ok(Some(mints)) => {
+                let derived = pricing::spot_prices(pool.sqrt_price, mints.token_a.decimals, mints.token_b.decimals);
+                Some((derived, mints))
+            }
+            Ok(None) => {
+                warn!(token_a_mint = %pool.token_a_mint, token_b_mint = %pool.token_b_mint, "⚠️ Mint metadata unavailable, skipping pool enrichment");
+                None
+            }
+            Err(e) => {
+                warn!(error = %e, "⚠️ Failed to load mint metadata, skipping pool enrichment");
+                None
+            }
+        }
+    }
 
-// Unit struct for Carbon pipeline compatibility
-pub struct MeteoraDammV2AccountProcessor;
+    // Store the update, refresh the cache and publish it to gateways
+    #[instrument(skip(self, update), fields(pipeline = %self.pipeline, pubkey = %update.pubkey, slot = update.slot))]
+    pub async fn store_and_publish(&self, update: NewAccountUpdate) -> Option<AccountUpdate> {
+        let pubkey = update.pubkey.clone();
+        let account_type = update.account_type.clone();
+
+        match self.database.insert_account_update(update).await {
+            Ok(account_update) => {
+                self.cache_and_publish(account_update.clone()).await;
+                Some(account_update)
+            }
+            Err(e) => {
+                error!(
+                    pubkey = %pubkey,
+                    account_type = %account_type,
+                    error = %e,
+                    "❌ Failed to store account in database"
+                );
+                None
+            }
+        }
+    }
+
+    // Announce a newly created pool on the new pools channel
+    pub async fn publish_new_pool(&self, account_update: &AccountUpdate, listing: PoolListing) {
+        info!(
+            pool = %account_update.pubkey,
+            token_a_mint = %listing.token_a_mint,
+            token_b_mint = %listing.token_b_mint,
+            slot = account_update.slot,
+            "🆕 Publishing new pool"
+        );
+
+        let new_pool = NewPool {
+            pool: account_update.pubkey.clone(),
+            program_id: account_update.program_id.clone(),
+            slot: account_update.slot,
+            token_a_mint: listing.token_a_mint,
+            token_b_mint: listing.token_b_mint,
+            config: listing.config,
+            transaction_signature: account_update.transaction_signature.clone(),
+            account: account_update.without_raw_data(),
+        };
+        if let Err(e) = self.fanout.publish(&FanoutEvent::NewPool(new_pool)).await {
+            warn!(pool = %account_update.pubkey, error = %e, "⚠️ Failed to publish new pool to gateways");
+        }
+    }
+
+    // Like store_and_publish, but leaves accounts alone that already have a row at the same or a newer slot.
+    // Returns whether the update was stored.
+    #[instrument(skip(self, update), fields(pipeline = %self.pipeline, pubkey = %update.pubkey, slot = update.slot))]
+    pub async fn store_if_newer_and_publish(&self, update: NewAccountUpdate) -> bool {
+        let pubkey = update.pubkey.clone();
+        let account_type = update.account_type.clone();
+
+        match self.database.insert_account_update_if_newer(update).await {
+            Ok(Some(account_update)) => {
+                self.cache_and_publish(account_update).await;
+                true
+            }
+            Ok(None) => false,
+            Err(e) => {
+                error!(
+                    pubkey = %pubkey,
+                    account_type = %account_type,
+                    error = %e,
+                    "❌ Failed to store account in database"
+                );
+                false
+            }
+        }
+    }
+
+    // Store a tombstone for an indexed account that was closed or handed to another owner, which also
+    // marks the cached copy and reaches subscribers as a `closed` message. Returns whether one was stored.
+    #[instrument(skip(self, program, account), fields(pipeline = %self.pipeline, pubkey = %pubkey, slot))]
+    pub async fn record_closure(
+        &self,
+        program: &IndexedProgram,
+        pubkey: &str,
+        slot: u64,
+        account: solana_account::Account,
+        transaction_signature: Option<String>,
+        origin: &str,
+    ) -> bool {
+        let program_id = program.program_id().to_string();
+
+        // Only accounts this program indexed get a tombstone, and only once
+        let latest = match self.database.get_latest_account_state(pubkey).await {
+            Ok(Some(latest)) if latest.program_id == program_id && !latest.is_tombstone() => latest,
+            Ok(_) => return false,
+            Err(e) => {
+                warn!(pubkey = %pubkey, error = %e, "⚠️ Failed to load latest account state, skipping closure");
+                return false;
+            }
+        };
+
+        let event_type = if account.lamports == 0 { "closed" } else { "owner_changed" };
+        info!(
+            pubkey = %pubkey,
+            slot,
+            account_type = %latest.account_type,
+            event_type,
+            owner = %account.owner,
+            "🪦 Recording account closure"
+        );
+
+        let update = NewAccountUpdate {
+            pubkey: pubkey.to_string(),
+            slot,
+            program_id,
+            account_type: latest.account_type,
+            owner: account.owner.to_string(),
+            lamports: account.lamports,
+            data_json: serde_json::Value::Null,
+            write_version: None,
+            transaction_signature,
+            origin: origin.to_string(),
+            commitment: self.commitment.as_str().to_string(),
+            data_hash: content_hash(&account),
+            executable: account.executable,
+            rent_epoch: account.rent_epoch,
+            data: account.data,
+            decoder_version: program.decoder_version().to_string(),
+            event_type: event_type.to_string(),
+            derived: None,
+            mints: None,
+        };
+
+        self.store_if_newer_and_publish(update).await
+    }
+
+    async fn cache_and_publish(&self, account_update: AccountUpdate) {
+        let pubkey = account_update.pubkey.clone();
+
+        if let Some(block_groups) = &self.block_groups {
+            block_groups.add_account(&account_update);
+        }
+        self.indexed_accounts.record(&account_update);
+
+        // Update cache, unless a newer slot got there first
+        match self.cache.set_account(&pubkey, &account_update).await {
+            Ok(true) => debug!(pubkey = %pubkey, "✅ Account cached successfully"),
+            Ok(false) => debug!(pubkey = %pubkey, "⏭️ Cache already holds a newer slot"),
+            Err(e) => {
+                warn!(
+                    pubkey = %pubkey,
+                    error = %e,
+                    "⚠️ Failed to cache account in Redis"
+                );
+            }
+        }
+
+        // Publish to gateways, which broadcast to their WebSocket clients
+        debug!(pubkey = %pubkey, "📡 Publishing account update to fanout channel");
+        if let Err(e) = self
+            .fanout
+            .publish(&FanoutEvent::AccountUpdate(account_update.clone()))
+            .await
+        {
+            warn!(
+                pubkey = %pubkey,
+                error = %e,
+                "⚠️ Failed to publish account update to gateways"
+            );
+        }
+
+        positions::index(self, &account_update).await;
+        valuation::revalue(self, &account_update).await;
+    }
+
+    // Store a decoded instruction and publish it to gateways
+    #[instrument(skip(self, event), fields(pipeline = %self.pipeline, signature = %event.signature, slot = event.slot))]
+    pub async fn store_and_publish_instruction(&self, event: NewInstructionEvent) {
+        let signature = event.signature.clone();
+
+        match self.database.insert_instruction_event(event).await {
+            Ok(instruction_event) => {
+                if let Some(block_groups) = &self.block_groups {
+                    block_groups.add_instruction(&instruction_event);
+                }
+
+                debug!(signature = %signature, "📡 Publishing instruction event to fanout channel");
+                if let Err(e) = self
+                    .fanout
+                    .publish(&FanoutEvent::InstructionEvent(instruction_event))
+                    .await
+                {
+                    warn!(
+                        signature = %signature,
+                        error = %e,
+                        "⚠️ Failed to publish instruction event to gateways"
+                    );
+                }
+            }
+            Err(e) => {
+                error!(
+                    signature = %signature,
+                    error = %e,
+                    "❌ Failed to store instruction event in database"
+                );
+            }
+        }
+    }
+}
+
+// Identifies an account's on-chain content, independent of the slot it was delivered at
+pub fn content_hash(account: &solana_account::Account) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(account.lamports.to_le_bytes());
+    hasher.update(account.owner.as_ref());
+    hasher.update(&account.data);
+    format!("{:x}", hasher.finalize())
+}
+
+// Stores decoded accounts of one registered program through the shared database, cache and fanout path
+pub struct AccountProcessor<A> {
+    context: ProcessorContext,
+    program: IndexedProgram,
+    _account: PhantomData<A>,
+}
+
+impl<A> AccountProcessor<A> {
+    pub fn new(context: ProcessorContext, program: IndexedProgram) -> Self {
+        Self {
+            context,
+            program,
+            _account: PhantomData,
+        }
+    }
+}
 
 #[async_trait]
-impl Processor for MeteoraDammV2AccountProcessor {
-    type InputType = AccountProcessorInputType<MeteoraDammV2Account>;
+impl<A: ProgramAccount> Processor for AccountProcessor<A> {
+    type InputType = AccountProcessorInputType<A>;
 
-    #[instrument(skip(self, input, _metrics), fields(pubkey = %input.0.pubkey, slot = input.0.slot))]
+    #[instrument(skip(self, input, _metrics), fields(program = self.program.name(), pubkey = %input.0.pubkey, slot = input.0.slot))]
     async fn process(
         &mut self,
         input: Self::InputType,
         _metrics: Arc<MetricsCollection>,
     ) -> Result<(), Error> {
         let (metadata, decoded_account, solana_account) = input;
+        self.context.record_slot(metadata.slot);
 
         info!(
             pubkey = %metadata.pubkey,
             slot = metadata.slot,
             lamports = solana_account.lamports,
             owner = %solana_account.owner,
-            "🔄 Processing Meteora DAMM V2 account update"
+            program = self.program.name(),
+            "🔄 Processing account update"
         );
 
-        // Get global state (should always be initialized by main.rs)
-        let state = PROCESSOR_STATE
-            .get()
-            .expect("Processor state not initialized");
+        // Drained accounts can still decode; the closure processor records them instead
+        if solana_account.lamports == 0 {
+            debug!(pubkey = %metadata.pubkey, program = self.program.name(), "⏭️ Skipping closed account");
+            return Ok(());
+        }
 
-        // Determine account type and serialize the actual data
-        let (account_type, account_json) = match decoded_account.data {
-            carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account::Pool(pool_data) => {
-                info!(pubkey = %metadata.pubkey, "🏊 Processing POOL account");
-                // With arbitrary_precision feature, u128 values are serialized as strings
-                (
-                    "Pool",
-                    serde_json::to_value(&pool_data).unwrap_or(serde_json::Value::Null),
-                )
+        let Some(account_type) = decoded_account.data.account_type() else {
+            debug!(pubkey = %metadata.pubkey, program = self.program.name(), "⏭️ Skipping unindexed account type");
+            return Ok(());
+        };
+        // Taken even for skipped updates, so the entry doesn't linger
+        let write_version = self.context.write_versions.take(&metadata.pubkey, metadata.slot);
+
+        let pubkey = metadata.pubkey.to_string();
+        let data_hash = content_hash(&solana_account);
+        if self.context.is_unchanged(&pubkey, metadata.slot, &data_hash).await {
+            return Ok(());
+        }
+
+        let account_json = decoded_account.data.to_json();
+        let listing = decoded_account.data.pool_listing();
+        let (derived, mints) = self.context.enrich_pool(decoded_account.data.sqrt_price()).await.unzip();
+
+        info!(account_type = %account_type, account_json = %account_json, "💾 Inserting account update into database");
+
+        // Create database record
+        let new_account_update = NewAccountUpdate {
+            pubkey,
+            slot: metadata.slot,
+            program_id: self.program.program_id().to_string(),
+            account_type: account_type.to_string(),
+            owner: solana_account.owner.to_string(),
+            lamports: solana_account.lamports,
+            data_json: account_json,
+            // Only the Yellowstone gRPC datasource reports write versions
+            write_version,
+            transaction_signature: metadata
+                .transaction_signature
+                .map(|signature| signature.to_string()),
+            origin: "live".to_string(),
+            commitment: self.context.commitment.as_str().to_string(),
+            data_hash,
+            data: solana_account.data,
+            executable: solana_account.executable,
+            rent_epoch: solana_account.rent_epoch,
+            decoder_version: self.program.decoder_version().to_string(),
+            event_type: "updated".to_string(),
+            derived,
+            mints,
+        };
+
+        let stored = self.context.store_and_publish(new_account_update).await;
+        if let (Some(account_update), Some(listing)) = (stored, listing) {
+            if account_update.event_type == "created" {
+                self.context.publish_new_pool(&account_update, listing).await;
             }
-            carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account::Position(
-                position_data,
-            ) => {
-                info!(pubkey = %metadata.pubkey, "📍 Processing POSITION account");
+        }
+
+        Ok(())
+    }
+}
+
+// Stores the pool-level Meteora DAMM V2 instructions and streams them to "trades" subscribers
+pub struct MeteoraDammV2InstructionProcessor {
+    context: ProcessorContext,
+}
+
+impl MeteoraDammV2InstructionProcessor {
+    pub fn new(context: ProcessorContext) -> Self {
+        Self { context }
+    }
+}
+
+#[async_trait]
+impl Processor for MeteoraDammV2InstructionProcessor {
+    type InputType = InstructionProcessorInputType<MeteoraDammV2Instruction>;
+
+    #[instrument(skip(self, input, _metrics), fields(signature = %input.0.transaction_metadata.signature, slot = input.0.transaction_metadata.slot))]
+    async fn process(
+        &mut self,
+        input: Self::InputType,
+        _metrics: Arc<MetricsCollection>,
+    ) -> Result<(), Error> {
+        let (metadata, decoded_instruction, _nested_instructions, _raw_instruction) = input;
+        self.context.record_slot(metadata.transaction_metadata.slot);
+
+        // A failed transaction's instructions changed nothing, so they aren't stored or streamed as trades
+        if metadata.transaction_metadata.meta.status.is_err() {
+            debug!(signature = %metadata.transaction_metadata.signature, "⏭️ Skipping instruction from failed transaction");
+            return Ok(());
+        }
+        let accounts = &decoded_instruction.accounts;
+
+        // Pool is located through each instruction's named account layout
+        let (instruction_type, pool, data_json) = match &decoded_instruction.data {
+            MeteoraDammV2Instruction::Swap(swap) => {
+                info!("🔄 Processing SWAP instruction");
                 (
-                    "Position",
-                    serde_json::to_value(&position_data).unwrap_or(serde_json::Value::Null),
+                    "Swap",
+                    Swap::arrange_accounts(accounts).map(|arranged| arranged.pool),
+                    serde_json::to_value(swap),
                 )
             }
-            carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account::Config(config_data) => {
-                info!(pubkey = %metadata.pubkey, "⚙️ Processing CONFIG account");
+            MeteoraDammV2Instruction::AddLiquidity(add_liquidity) => {
+                info!("💧 Processing ADD LIQUIDITY instruction");
                 (
-                    "Config",
-                    serde_json::to_value(&config_data).unwrap_or(serde_json::Value::Null),
+                    "AddLiquidity",
+                    AddLiquidity::arrange_accounts(accounts).map(|arranged| arranged.pool),
+                    serde_json::to_value(add_liquidity),
                 )
             }
-            carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account::ClaimFeeOperator(
-                operator_data,
-            ) => {
-                info!(pubkey = %metadata.pubkey, "💰 Processing CLAIM FEE OPERATOR account");
+            MeteoraDammV2Instruction::RemoveLiquidity(remove_liquidity) => {
+                info!("🚰 Processing REMOVE LIQUIDITY instruction");
                 (
-                    "ClaimFeeOperator",
-                    serde_json::to_value(&operator_data).unwrap_or(serde_json::Value::Null),
+                    "RemoveLiquidity",
+                    RemoveLiquidity::arrange_accounts(accounts).map(|arranged| arranged.pool),
+                    serde_json::to_value(remove_liquidity),
                 )
             }
-            carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account::TokenBadge(
-                badge_data,
-            ) => {
-                info!(pubkey = %metadata.pubkey, "🏆 Processing TOKEN BADGE account");
+            MeteoraDammV2Instruction::CreatePosition(create_position) => {
+                info!("📍 Processing CREATE POSITION instruction");
                 (
-                    "TokenBadge",
-                    serde_json::to_value(&badge_data).unwrap_or(serde_json::Value::Null),
+                    "CreatePosition",
+                    CreatePosition::arrange_accounts(accounts).map(|arranged| arranged.pool),
+                    serde_json::to_value(create_position),
                 )
             }
-            carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account::Vesting(
-                vesting_data,
-            ) => {
-                info!(pubkey = %metadata.pubkey, "🔒 Processing VESTING account");
+            MeteoraDammV2Instruction::ClaimReward(claim_reward) => {
+                info!("💰 Processing CLAIM REWARD instruction");
                 (
-                    "Vesting",
-                    serde_json::to_value(&vesting_data).unwrap_or(serde_json::Value::Null),
+                    "ClaimReward",
+                    ClaimReward::arrange_accounts(accounts).map(|arranged| arranged.pool),
+                    serde_json::to_value(claim_reward),
                 )
             }
+            _ => {
+                debug!("⏭️ Skipping unindexed instruction type");
+                return Ok(());
+            }
         };
 
-        info!(account_type = %account_type, account_json = %account_json, "💾 Inserting account update into database");
-
-        // Create database record
-        let new_account_update = NewAccountUpdate {
-            pubkey: metadata.pubkey.to_string(),
-            slot: metadata.slot,
-            account_type: account_type.to_string(),
-            owner: solana_account.owner.to_string(),
-            lamports: solana_account.lamports,
-            data_json: account_json,
+        let new_instruction_event = NewInstructionEvent {
+            signature: metadata.transaction_metadata.signature.to_string(),
+            slot: metadata.transaction_metadata.slot,
+            instruction_index: metadata.index,
+            program_id: decoded_instruction.program_id.to_string(),
+            instruction_type: instruction_type.to_string(),
+            pool: pool.map(|pool| pool.to_string()),
+            accounts: accounts.iter().map(|meta| meta.pubkey.to_string()).collect(),
+            data_json: data_json.unwrap_or(serde_json::Value::Null),
         };
 
-        // Store in database
-        // debug!(
-        //     pubkey = %metadata.pubkey,
-        //     account_type,
-        //     slot = metadata.slot,
-        //     "💾 Inserting account update into database"
-        // );
-
-        match state
-            .database
-            .insert_account_update(new_account_update)
-            .await
-        {
-            Ok(account_update) => {
-                // Update cache
+        self.context
+            .store_and_publish_instruction(new_instruction_event)
+            .await;
 
-                if let Err(e) = state
-                    .cache
-                    .set_account(&metadata.pubkey.to_string(), &account_update)
-                    .await
-                {
-                    warn!(
-                        pubkey = %metadata.pubkey,
-                        error = %e,
-                        "⚠️ Failed to cache account in Redis"
-                    );
-                } else {
-                    debug!(pubkey = %metadata.pubkey, "✅ Account cached successfully");
-                }
+        Ok(())
+    }
+}
 
-                // Broadcast to WebSocket clients
-                debug!(pubkey = %metadata.pubkey, "📡 Broadcasting account update to WebSocket clients");
-                state
-                    .websocket_server
-                    .broadcast_account_update(&metadata.pubkey.to_string(), &account_update)
-                    .await;
-            }
-            Err(e) => {
-                error!(
-                    pubkey = %metadata.pubkey,
-                    account_type,
-                    error = %e,
-                    "❌ Failed to store account in database"
-                );
-            }
+// Publishes everything stored for a block as one message once the block's details arrive
+pub struct BlockProcessor {
+    context: ProcessorContext,
+}
+
+impl BlockProcessor {
+    pub fn new(context: ProcessorContext) -> Self {
+        Self { context }
+    }
+}
+
+#[async_trait]
+impl Processor for BlockProcessor {
+    type InputType = BlockDetails;
+
+    #[instrument(skip(self, input, _metrics), fields(slot = input.slot))]
+    async fn process(
+        &mut self,
+        input: Self::InputType,
+        _metrics: Arc<MetricsCollection>,
+    ) -> Result<(), Error> {
+        let Some(block) = self
+            .context
+            .block_groups
+            .as_ref()
+            .and_then(|block_groups| block_groups.finish(input.slot))
+        else {
+            return Ok(());
+        };
+
+        info!(
+            slot = block.slot,
+            account_count = block.accounts.len(),
+            instruction_count = block.instructions.len(),
+            "🧱 Publishing block update"
+        );
+
+        if let Err(e) = self.context.fanout.publish(&FanoutEvent::Block(block)).await {
+            warn!(slot = input.slot, error = %e, "⚠️ Failed to publish block update to gateways");
         }
 
         Ok(())
     }
 }
-
-// #[async_trait]
-// impl Processor for DriftAccountProcessor {
-//     type InputType = AccountProcessorInputType<DriftAccount>;
-
-//     async fn process(
-//         &mut self,
-//         input: Self::InputType,
-//         _metrics: Arc<MetricsCollection>,
-//     ) -> Result<(), Error> {
-//         println!("🔥 DRIFT PROCESSOR CALLED!");
-//         let (_metadata, decoded_account, _solana_account) = input;
-
-//         // println!("=== Account Update ===");
-//         // println!("Account: {}", metadata.pubkey);
-//         // println!("Slot: {}", metadata.slot);
-
-//         // println!("Owner: {}", solana_account.owner);
-//         // println!("Lamports: {}", solana_account.lamports);
-
-//         // println!("Decoded Account Lamports: {}", decoded_account.lamports);
-//         // println!("Decoded Account Owner: {}", decoded_account.owner);
-//         // println!("Decoded Account Executable: {}", decoded_account.executable);
-//         // println!("Decoded Account Rent Epoch: {}", decoded_account.rent_epoch);
-
-//         match decoded_account.data {
-//             carbon_drift_v2_decoder::accounts::DriftAccount::User(user) => {
-//                 println!("🎯 USER ACCOUNT FOUND!");
-//                 println!("User: {:?}", user);
-//             }
-
-//             _ => {
-//                 println!("❓ Other account type (skipping)");
-//                 return Ok(());
-//             }
-//         }
-
-//         println!("===================");
-
-//         Ok(())
-//     }
-// }
-
-// #[async_trait]
-// impl Processor for MeteoraDammV2InstructionProcessor {
-//     type InputType = InstructionProcessorInputType<MeteoraDammV2Instruction>;
-
-//     async fn process(
-//         &mut self,
-//         input: Self::InputType,
-//         _metrics: Arc<MetricsCollection>,
-//     ) -> Result<(), Error> {
-//         println!("🎯 METEORA INSTRUCTION PROCESSOR CALLED!");
-//         let (metadata, decoded_instruction, _nested_instructions, _raw_instruction) = input;
-
-//         println!(
-//             "Transaction Signature: {:?}",
-//             metadata.transaction_metadata.signature
-//         );
-//         println!("Slot: {}", metadata.transaction_metadata.slot);
-//         println!("Program ID: {}", decoded_instruction.program_id);
-
-//         match decoded_instruction.data {
-//             MeteoraDammV2Instruction::Swap(swap) => {
-//                 println!("🔄 SWAP INSTRUCTION!");
-//                 println!("Swap: {:?}", swap);
-//             }
-//             MeteoraDammV2Instruction::AddLiquidity(add_liquidity) => {
-//                 println!("💧 ADD LIQUIDITY INSTRUCTION!");
-//                 println!("Add Liquidity: {:?}", add_liquidity);
-//             }
-//             MeteoraDammV2Instruction::RemoveLiquidity(remove_liquidity) => {
-//                 println!("🚰 REMOVE LIQUIDITY INSTRUCTION!");
-//                 println!("Remove Liquidity: {:?}", remove_liquidity);
-//             }
-//             MeteoraDammV2Instruction::CreatePosition(create_position) => {
-//                 println!("📍 CREATE POSITION INSTRUCTION!");
-//                 println!("Create Position: {:?}", create_position);
-//             }
-//             MeteoraDammV2Instruction::ClosePosition(close_position) => {
-//                 println!("❌ CLOSE POSITION INSTRUCTION!");
-//                 println!("Close Position: {:?}", close_position);
-//             }
-//             MeteoraDammV2Instruction::InitializePool(initialize_pool) => {
-//                 println!("🏊 INITIALIZE POOL INSTRUCTION!");
-//                 println!("Initialize Pool: {:?}", initialize_pool);
-//             }
-//             MeteoraDammV2Instruction::ClaimReward(claim_reward) => {
-//                 println!("💰 CLAIM REWARD INSTRUCTION!");
-//                 println!("Claim Reward: {:?}", claim_reward);
-//             }
-//             MeteoraDammV2Instruction::LockPosition(lock_position) => {
-//                 println!("🔒 LOCK POSITION INSTRUCTION!");
-//                 println!("Lock Position: {:?}", lock_position);
-//             }
-//             _ => {
-//                 println!("📋 Other instruction type");
-//             }
-//         }
-
-//         println!("===================");
-
-//         Ok(())
-//     }
-// }
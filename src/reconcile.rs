@@ -0,0 +1,156 @@
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::ClientError;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcAccountInfoConfig;
+use solana_pubkey::Pubkey;
+use std::str::FromStr;
+use tracing::{info, warn, debug, instrument};
+
+use crate::config::{PipelineConfig, RpcEndpoint};
+use crate::database::{KnownAccount, NewAccountUpdate};
+use crate::processor::{ProcessorContext, content_hash};
+use crate::registry::IndexedProgram;
+
+// getMultipleAccounts accepts at most 100 pubkeys per call
+const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+#[derive(Debug, Default)]
+pub struct ReconcileStats {
+    pub checked: usize,
+    pub reconciled: usize,
+    pub closed: usize,
+}
+
+// Re-read every known account of the pipeline's programs after a reconnect, and emit a
+// `reconciled` update wherever the chain moved on while the stream was down
+#[instrument(skip(config, endpoint, context), fields(pipeline = %config.name))]
+pub async fn reconcile_pipeline(
+    config: &PipelineConfig,
+    endpoint: &RpcEndpoint,
+    context: &ProcessorContext,
+    since_slot: u64,
+) -> Result<(), ClientError> {
+    let rpc_client = RpcClient::new_with_commitment(endpoint.http_url.clone(), context.commitment.rpc_config());
+
+    for program in &config.programs {
+        let stats = reconcile_program(&rpc_client, program, context, since_slot).await?;
+        info!(
+            pipeline = %config.name,
+            program = %program.name(),
+            since_slot,
+            checked = stats.checked,
+            reconciled = stats.reconciled,
+            closed = stats.closed,
+            "✅ Reconciliation complete"
+        );
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(rpc_client, program, context), fields(program = %program.name()))]
+async fn reconcile_program(
+    rpc_client: &RpcClient,
+    program: &IndexedProgram,
+    context: &ProcessorContext,
+    since_slot: u64,
+) -> Result<ReconcileStats, ClientError> {
+    let program_id = program.program_id();
+
+    let known: Vec<(Pubkey, KnownAccount)> = match context.database.get_known_accounts(&program_id.to_string()).await {
+        Ok(known) => known
+            .into_iter()
+            .filter_map(|account| Some((Pubkey::from_str(&account.pubkey).ok()?, account)))
+            .collect(),
+        Err(e) => {
+            warn!(program = %program.name(), error = %e, "⚠️ Failed to load known accounts, skipping reconciliation");
+            return Ok(ReconcileStats::default());
+        }
+    };
+
+    let mut stats = ReconcileStats::default();
+
+    for chunk in known.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let pubkeys: Vec<Pubkey> = chunk.iter().map(|(pubkey, _)| *pubkey).collect();
+
+        let response = rpc_client
+            .get_multiple_accounts_with_config(
+                &pubkeys,
+                RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    min_context_slot: Some(since_slot),
+                    ..Default::default()
+                },
+            )
+            .await?;
+        let slot = response.context.slot;
+
+        for ((_, stored), account) in chunk.iter().zip(response.value) {
+            stats.checked += 1;
+
+            // Closed or reassigned while disconnected
+            let account = account.unwrap_or_default();
+            if account.lamports == 0 || account.owner != program_id {
+                if context
+                    .record_closure(program, &stored.pubkey, slot, account, None, "reconciled")
+                    .await
+                {
+                    stats.closed += 1;
+                }
+                continue;
+            }
+
+            if stored.slot >= slot as i64 {
+                continue;
+            }
+
+            // The content hash covers lamports, owner and raw data; older rows without one compare decoded data
+            let data_hash = content_hash(&account);
+            match &stored.data_hash {
+                Some(stored_hash) if *stored_hash == data_hash => continue,
+                Some(_) => {}
+                None => {
+                    let unchanged = stored.lamports == account.lamports as i64
+                        && program.decode_account(&account).is_some_and(|(_, data_json)| data_json == stored.data_json);
+                    if unchanged {
+                        continue;
+                    }
+                }
+            }
+
+            let Some((account_type, data_json)) = program.decode_account(&account) else {
+                continue;
+            };
+
+            debug!(pubkey = %stored.pubkey, stored_slot = stored.slot, slot, "🩹 Account changed while disconnected");
+            let (derived, mints) = context.enrich_pool(program.sqrt_price(&account)).await.unzip();
+            let update = NewAccountUpdate {
+                pubkey: stored.pubkey.clone(),
+                slot,
+                program_id: program_id.to_string(),
+                account_type,
+                owner: account.owner.to_string(),
+                lamports: account.lamports,
+                data_json,
+                write_version: None,
+                transaction_signature: None,
+                origin: "reconciled".to_string(),
+                commitment: context.commitment.as_str().to_string(),
+                data_hash,
+                executable: account.executable,
+                rent_epoch: account.rent_epoch,
+                data: account.data,
+                decoder_version: program.decoder_version().to_string(),
+                event_type: "updated".to_string(),
+                derived,
+                mints,
+            };
+
+            if context.store_if_newer_and_publish(update).await {
+                stats.reconciled += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,124 @@
+use tracing::{info, warn, instrument};
+
+use crate::config::{PipelineConfig, RedecodeConfig};
+use crate::database::StoredRawAccount;
+use crate::processor::ProcessorContext;
+use crate::registry::IndexedProgram;
+
+#[derive(Debug, Default)]
+pub struct RedecodeStats {
+    pub processed: usize,
+    pub updated: usize,
+    // Stored bytes the current decoder rejects; the row keeps its previous JSON
+    pub failed: usize,
+}
+
+// Re-run the current decoder of every program in the pipeline over its stored raw account data
+#[instrument(skip(config, redecode, context), fields(pipeline = %config.name))]
+pub async fn redecode_pipeline(
+    config: &PipelineConfig,
+    redecode: RedecodeConfig,
+    context: &ProcessorContext,
+) -> Result<(), sqlx::Error> {
+    for program in &config.programs {
+        let stats = redecode_program(program, redecode, context).await?;
+        info!(
+            pipeline = %config.name,
+            program = %program.name(),
+            decoder_version = %program.decoder_version(),
+            processed = stats.processed,
+            updated = stats.updated,
+            failed = stats.failed,
+            "✅ Re-decode complete"
+        );
+    }
+
+    Ok(())
+}
+
+#[instrument(skip(program, redecode, context), fields(program = %program.name()))]
+async fn redecode_program(
+    program: &IndexedProgram,
+    redecode: RedecodeConfig,
+    context: &ProcessorContext,
+) -> Result<RedecodeStats, sqlx::Error> {
+    let program_id = program.program_id().to_string();
+    let decoder_version = program.decoder_version();
+    info!(program = %program.name(), decoder_version, new_version = redecode.new_version, "🔁 Re-decoding stored accounts");
+
+    let mut stats = RedecodeStats::default();
+    let mut after_id = 0;
+
+    loop {
+        let batch = context
+            .database
+            .get_redecode_batch(
+                &program_id,
+                decoder_version,
+                redecode.new_version,
+                after_id,
+                redecode.batch_size as i64,
+            )
+            .await?;
+        let Some(last) = batch.last() else {
+            break;
+        };
+        after_id = last.id;
+
+        for row in batch {
+            stats.processed += 1;
+
+            let Some((account_type, data_json)) = row.to_account().and_then(|account| program.decode_account(&account)) else {
+                warn!(id = row.id, pubkey = %row.pubkey, "⚠️ Stored account no longer decodes, keeping previous JSON");
+                stats.failed += 1;
+                continue;
+            };
+
+            if redecode.new_version {
+                context
+                    .database
+                    .insert_decoded_version(row.id, &account_type, &data_json, decoder_version)
+                    .await?;
+            } else {
+                context
+                    .database
+                    .update_decoded(row.id, &account_type, &data_json, decoder_version)
+                    .await?;
+                refresh_cached(context, &row, &account_type, &data_json).await;
+            }
+            stats.updated += 1;
+        }
+
+        info!(
+            program = %program.name(),
+            processed = stats.processed,
+            updated = stats.updated,
+            failed = stats.failed,
+            last_id = after_id,
+            "📈 Re-decode progress"
+        );
+    }
+
+    Ok(stats)
+}
+
+// Keep the cached copy in step when it is the row that was re-decoded
+async fn refresh_cached(
+    context: &ProcessorContext,
+    row: &StoredRawAccount,
+    account_type: &str,
+    data_json: &serde_json::Value,
+) {
+    let Ok(Some(mut cached)) = context.cache.get_account(&row.pubkey).await else {
+        return;
+    };
+    if cached.id != row.id {
+        return;
+    }
+
+    cached.account_type = account_type.to_string();
+    cached.data_json = data_json.clone();
+    if let Err(e) = context.cache.set_account(&row.pubkey, &cached).await {
+        warn!(pubkey = %row.pubkey, error = %e, "⚠️ Failed to refresh cached account");
+    }
+}
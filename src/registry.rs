@@ -0,0 +1,290 @@
+use carbon_core::account::{AccountDecoder, DecodedAccount};
+use carbon_core::pipeline::PipelineBuilder;
+use carbon_drift_v2_decoder::DriftDecoder;
+use carbon_drift_v2_decoder::accounts::DriftAccount;
+use carbon_meteora_damm_v2_decoder::MeteoraDammV2Decoder;
+use carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use solana_pubkey::Pubkey;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::closure::{ClosureDecoder, ClosureProcessor};
+use crate::config::ConfigError;
+use crate::database::AccountUpdate;
+use crate::pricing::SqrtPrice;
+use crate::idl::{self, DISCRIMINATOR_LEN, IdlAccount, IdlDecoder, IdlProgram};
+use crate::processor::{AccountProcessor, MeteoraDammV2InstructionProcessor, ProcessorContext};
+
+// Recorded with every decoded row; build.rs reads the resolved versions from Cargo.lock,
+// so any decoder upgrade, patch releases included, gets stored rows re-decoded
+const METEORA_DAMM_V2_DECODER_VERSION: &str = env!("METEORA_DAMM_V2_DECODER_VERSION");
+const DRIFT_V2_DECODER_VERSION: &str = env!("DRIFT_V2_DECODER_VERSION");
+
+// Anchor account names with filter presets; they match the names in account_updates.account_type
+const METEORA_DAMM_V2_ACCOUNT_TYPES: &[&str] =
+    &["Pool", "Position", "Config", "ClaimFeeOperator", "TokenBadge", "Vesting"];
+const DRIFT_V2_ACCOUNT_TYPES: &[&str] = &["User", "PerpMarket", "SpotMarket", "State"];
+
+// Decoded accounts of a supported program, named and converted the same way for storage
+pub trait ProgramAccount: Send + Sync + 'static {
+    // Name stored in account_updates.account_type, or None for account types that are not indexed
+    fn account_type(&self) -> Option<&str>;
+
+    fn to_json(&self) -> Value;
+
+    // Mints and settings of a liquidity pool account, announced on the new pools channel when it is created
+    fn pool_listing(&self) -> Option<PoolListing> {
+        None
+    }
+
+    // Mints and Q64.64 sqrt_price of a pool account, for the derived spot prices
+    fn sqrt_price(&self) -> Option<SqrtPrice> {
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PoolListing {
+    pub token_a_mint: String,
+    pub token_b_mint: String,
+    pub config: Value,
+}
+
+// Sent to new pool subscribers of the program when a pool account is first seen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewPool {
+    pub pool: String,
+    pub program_id: String,
+    pub slot: i64,
+    pub token_a_mint: String,
+    pub token_b_mint: String,
+    // Fee schedule, activation and price range the pool was created with
+    pub config: Value,
+    pub transaction_signature: Option<String>,
+    pub account: AccountUpdate,
+}
+
+// Every program the indexer can decode; adding one means a variant here and a ProgramAccount impl.
+// Programs without a Carbon decoder crate can be indexed from an Anchor IDL loaded at startup.
+#[derive(Clone, Debug)]
+pub enum IndexedProgram {
+    MeteoraDammV2,
+    DriftV2,
+    Idl(Arc<IdlProgram>),
+}
+
+impl IndexedProgram {
+    pub fn name(&self) -> &str {
+        match self {
+            IndexedProgram::MeteoraDammV2 => "meteora_damm_v2",
+            IndexedProgram::DriftV2 => "drift_v2",
+            IndexedProgram::Idl(program) => &program.name,
+        }
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        match self {
+            IndexedProgram::MeteoraDammV2 => carbon_meteora_damm_v2_decoder::PROGRAM_ID,
+            IndexedProgram::DriftV2 => carbon_drift_v2_decoder::PROGRAM_ID,
+            IndexedProgram::Idl(program) => program.program_id,
+        }
+    }
+
+    pub fn decoder_version(&self) -> &str {
+        match self {
+            IndexedProgram::MeteoraDammV2 => METEORA_DAMM_V2_DECODER_VERSION,
+            IndexedProgram::DriftV2 => DRIFT_V2_DECODER_VERSION,
+            IndexedProgram::Idl(program) => &program.version,
+        }
+    }
+
+    // Attach this program's decoder and a processor tagging its updates with the program id,
+    // plus the closure pipe for accounts that stop decoding once closed or reassigned
+    pub fn add_to_pipeline(&self, builder: PipelineBuilder, context: ProcessorContext) -> PipelineBuilder {
+        let builder = match self {
+            IndexedProgram::MeteoraDammV2 => builder.account(
+                MeteoraDammV2Decoder,
+                AccountProcessor::<MeteoraDammV2Account>::new(context.clone(), self.clone()),
+            ),
+            IndexedProgram::DriftV2 => builder.account(
+                DriftDecoder,
+                AccountProcessor::<DriftAccount>::new(context.clone(), self.clone()),
+            ),
+            IndexedProgram::Idl(program) => builder.account(
+                IdlDecoder::new(program.clone()),
+                AccountProcessor::<IdlAccount>::new(context.clone(), self.clone()),
+            ),
+        };
+        builder.account(
+            ClosureDecoder::new(self.program_id()),
+            ClosureProcessor::new(context, self.clone()),
+        )
+    }
+
+    // Decode a raw account outside a pipeline, returning its stored account type and JSON
+    pub fn decode_account(&self, account: &solana_account::Account) -> Option<(String, Value)> {
+        match self {
+            IndexedProgram::MeteoraDammV2 => stored_form(MeteoraDammV2Decoder.decode_account(account)),
+            IndexedProgram::DriftV2 => stored_form(DriftDecoder.decode_account(account)),
+            IndexedProgram::Idl(program) => stored_form(IdlDecoder::new(program.clone()).decode_account(account)),
+        }
+    }
+
+    // Pricing inputs of a raw pool account, for paths that decode outside a pipeline
+    pub fn sqrt_price(&self, account: &solana_account::Account) -> Option<SqrtPrice> {
+        match self {
+            IndexedProgram::MeteoraDammV2 => MeteoraDammV2Decoder.decode_account(account)?.data.sqrt_price(),
+            IndexedProgram::DriftV2 | IndexedProgram::Idl(_) => None,
+        }
+    }
+
+    // Anchor discriminator of a named account type, used for memcmp filter presets
+    pub fn account_discriminator(&self, account_type: &str) -> Option<[u8; DISCRIMINATOR_LEN]> {
+        match self {
+            IndexedProgram::MeteoraDammV2 => METEORA_DAMM_V2_ACCOUNT_TYPES
+                .contains(&account_type)
+                .then(|| idl::account_discriminator(account_type)),
+            IndexedProgram::DriftV2 => DRIFT_V2_ACCOUNT_TYPES
+                .contains(&account_type)
+                .then(|| idl::account_discriminator(account_type)),
+            IndexedProgram::Idl(program) => program.discriminator(account_type),
+        }
+    }
+
+    pub fn indexes_instructions(&self) -> bool {
+        matches!(self, IndexedProgram::MeteoraDammV2)
+    }
+
+    // Attach this program's instruction decoder; a no-op for programs whose instructions are not indexed
+    pub fn add_instructions_to_pipeline(&self, builder: PipelineBuilder, context: ProcessorContext) -> PipelineBuilder {
+        match self {
+            IndexedProgram::MeteoraDammV2 => builder.instruction(
+                MeteoraDammV2Decoder,
+                MeteoraDammV2InstructionProcessor::new(context),
+            ),
+            IndexedProgram::DriftV2 | IndexedProgram::Idl(_) => builder,
+        }
+    }
+}
+
+fn stored_form<A: ProgramAccount>(decoded: Option<DecodedAccount<A>>) -> Option<(String, Value)> {
+    let decoded = decoded?;
+    let account_type = decoded.data.account_type()?.to_string();
+    Some((account_type, decoded.data.to_json()))
+}
+
+impl FromStr for IndexedProgram {
+    type Err = ConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "meteora_damm_v2" => Ok(IndexedProgram::MeteoraDammV2),
+            "drift_v2" => Ok(IndexedProgram::DriftV2),
+            other => match other.strip_prefix("idl:") {
+                Some(path) => IdlProgram::load(path)
+                    .map(|program| IndexedProgram::Idl(Arc::new(program)))
+                    .map_err(|e| ConfigError::InvalidIdl {
+                        path: path.to_string(),
+                        reason: e.to_string(),
+                    }),
+                None => Err(ConfigError::InvalidProgram(other.to_string())),
+            },
+        }
+    }
+}
+
+impl ProgramAccount for MeteoraDammV2Account {
+    fn account_type(&self) -> Option<&str> {
+        Some(match self {
+            MeteoraDammV2Account::Pool(_) => "Pool",
+            MeteoraDammV2Account::Position(_) => "Position",
+            MeteoraDammV2Account::Config(_) => "Config",
+            MeteoraDammV2Account::ClaimFeeOperator(_) => "ClaimFeeOperator",
+            MeteoraDammV2Account::TokenBadge(_) => "TokenBadge",
+            MeteoraDammV2Account::Vesting(_) => "Vesting",
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        // With arbitrary_precision feature, u128 values are serialized as strings
+        match self {
+            MeteoraDammV2Account::Pool(pool_data) => serde_json::to_value(pool_data),
+            MeteoraDammV2Account::Position(position_data) => serde_json::to_value(position_data),
+            MeteoraDammV2Account::Config(config_data) => serde_json::to_value(config_data),
+            MeteoraDammV2Account::ClaimFeeOperator(operator_data) => serde_json::to_value(operator_data),
+            MeteoraDammV2Account::TokenBadge(badge_data) => serde_json::to_value(badge_data),
+            MeteoraDammV2Account::Vesting(vesting_data) => serde_json::to_value(vesting_data),
+        }
+        .unwrap_or(Value::Null)
+    }
+
+    fn pool_listing(&self) -> Option<PoolListing> {
+        let MeteoraDammV2Account::Pool(pool) = self else {
+            return None;
+        };
+
+        Some(PoolListing {
+            token_a_mint: pool.token_a_mint.to_string(),
+            token_b_mint: pool.token_b_mint.to_string(),
+            config: json!({
+                "pool_fees": pool.pool_fees,
+                "collect_fee_mode": pool.collect_fee_mode,
+                "pool_type": pool.pool_type,
+                "activation_type": pool.activation_type,
+                "activation_point": pool.activation_point,
+                "sqrt_min_price": pool.sqrt_min_price,
+                "sqrt_max_price": pool.sqrt_max_price,
+                "creator": pool.creator.to_string(),
+                "partner": pool.partner.to_string(),
+            }),
+        })
+    }
+
+    fn sqrt_price(&self) -> Option<SqrtPrice> {
+        let MeteoraDammV2Account::Pool(pool) = self else {
+            return None;
+        };
+
+        Some(SqrtPrice {
+            token_a_mint: pool.token_a_mint,
+            token_b_mint: pool.token_b_mint,
+            sqrt_price: pool.sqrt_price,
+        })
+    }
+}
+
+impl ProgramAccount for DriftAccount {
+    // Only the account types the risk team watches are indexed
+    fn account_type(&self) -> Option<&str> {
+        match self {
+            DriftAccount::User(_) => Some("User"),
+            DriftAccount::PerpMarket(_) => Some("PerpMarket"),
+            DriftAccount::SpotMarket(_) => Some("SpotMarket"),
+            DriftAccount::State(_) => Some("State"),
+            _ => None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match self {
+            DriftAccount::User(user_data) => serde_json::to_value(user_data),
+            DriftAccount::PerpMarket(perp_market_data) => serde_json::to_value(perp_market_data),
+            DriftAccount::SpotMarket(spot_market_data) => serde_json::to_value(spot_market_data),
+            DriftAccount::State(state_data) => serde_json::to_value(state_data),
+            _ => Ok(Value::Null),
+        }
+        .unwrap_or(Value::Null)
+    }
+}
+
+impl ProgramAccount for IdlAccount {
+    fn account_type(&self) -> Option<&str> {
+        Some(&self.name)
+    }
+
+    fn to_json(&self) -> Value {
+        self.value.clone()
+    }
+}
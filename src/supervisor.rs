@@ -0,0 +1,182 @@
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+use tracing::{info, warn, error, instrument};
+
+use crate::backfill;
+use crate::closure;
+use crate::commitment::{self, Commitment};
+use crate::config::{PipelineConfig, RpcEndpoint};
+use crate::pipeline;
+use crate::portfolio;
+use crate::positions;
+use crate::processor::ProcessorContext;
+use crate::reconcile;
+use crate::valuation;
+use crate::registry::IndexedProgram;
+
+// A pipeline that stayed up this long is considered recovered and its backoff is reset
+const STABLE_RUN: Duration = Duration::from_secs(60);
+
+// How often a pipeline with deduplication reports how many unchanged updates it skipped
+const DEDUP_REPORT_INTERVAL: Duration = Duration::from_secs(60);
+
+// Keeps a pipeline running: picks a healthy endpoint, rebuilds the Carbon pipeline whenever it
+// stops, and backs off exponentially between attempts instead of exiting the process
+#[instrument(skip(config, context), fields(pipeline = %config.name))]
+pub async fn supervise(config: PipelineConfig, context: ProcessorContext) {
+    let initial_backoff = Duration::from_millis(config.reconnect.initial_backoff_ms);
+    let max_backoff = Duration::from_millis(config.reconnect.max_backoff_ms);
+    let mut backoff = initial_backoff;
+    let mut next_endpoint = 0;
+    let mut attempt: u64 = 0;
+
+    // Before the first update arrives, so closures of existing accounts are recognised
+    context.indexed_accounts.seed(&context.database, &config.programs).await;
+    tokio::spawn(closure::refresh_indexed_accounts(config.programs.clone(), context.clone()));
+
+    if config.dedup.enabled {
+        tokio::spawn(report_skipped_updates(config.name.clone(), context.clone()));
+    }
+    if indexes_positions(&config) {
+        tokio::spawn(positions::seed(context.clone()));
+        tokio::spawn(valuation::run(context.clone()));
+    }
+
+    loop {
+        let Some(index) = find_healthy_endpoint(&config.endpoints, next_endpoint).await else {
+            warn!(
+                pipeline = %config.name,
+                backoff_ms = backoff.as_millis() as u64,
+                "⚠️ No healthy RPC endpoint available, retrying"
+            );
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(max_backoff);
+            continue;
+        };
+        let endpoint = &config.endpoints[index];
+
+        if attempt > 0 {
+            log_reconnect(&config, endpoint, &context, attempt).await;
+            spawn_reconciliation(&config, endpoint, &context);
+        } else if config.backfill_on_startup {
+            // A failed backfill is logged but doesn't hold back the live stream
+            if let Err(e) = backfill::backfill_pipeline(&config, endpoint, &context).await {
+                error!(pipeline = %config.name, endpoint = %endpoint.http_url, error = %e, "❌ Startup backfill failed");
+            }
+        }
+        attempt += 1;
+
+        // Processed pipelines need the tracker for upgrades and rollbacks; it follows the pipeline's endpoint
+        let tracker = (config.commitment == Commitment::Processed).then(|| {
+            let program_ids = config.programs.iter().map(|program| program.program_id().to_string()).collect();
+            tokio::spawn(commitment::track(endpoint.clone(), program_ids, context.clone()))
+        });
+        // Only Meteora DAMM V2 positions are held through NFTs
+        let portfolio_tracker = indexes_positions(&config)
+            .then(|| tokio::spawn(portfolio::track(endpoint.clone(), context.clone())));
+
+        let started = Instant::now();
+        match pipeline::run_pipeline(&config, endpoint, context.clone()).await {
+            Ok(()) => warn!(pipeline = %config.name, endpoint = %endpoint.ws_url, "⚠️ Carbon pipeline stopped"),
+            Err(e) => error!(pipeline = %config.name, endpoint = %endpoint.ws_url, error = %e, "❌ Carbon pipeline failed"),
+        }
+        for tracker in [tracker, portfolio_tracker].into_iter().flatten() {
+            tracker.abort();
+        }
+
+        if started.elapsed() >= STABLE_RUN {
+            backoff = initial_backoff;
+        }
+
+        // Fail over to the next endpoint in the list
+        next_endpoint = (index + 1) % config.endpoints.len();
+        info!(
+            pipeline = %config.name,
+            backoff_ms = backoff.as_millis() as u64,
+            "🔁 Rebuilding Carbon pipeline after backoff"
+        );
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
+
+// First endpoint, starting at `start`, that answers getHealth
+async fn find_healthy_endpoint(endpoints: &[RpcEndpoint], start: usize) -> Option<usize> {
+    for offset in 0..endpoints.len() {
+        let index = (start + offset) % endpoints.len();
+        let endpoint = &endpoints[index];
+
+        match RpcClient::new(endpoint.http_url.clone()).get_health().await {
+            Ok(()) => return Some(index),
+            Err(e) => warn!(endpoint = %endpoint.http_url, error = %e, "⚠️ RPC endpoint failed health check"),
+        }
+    }
+    None
+}
+
+// Catch up on changes missed while disconnected, alongside the restarted stream.
+// Rows are only written when nothing newer is stored, so it can't race live updates.
+fn spawn_reconciliation(config: &PipelineConfig, endpoint: &RpcEndpoint, context: &ProcessorContext) {
+    let since_slot = context.last_slot();
+    if since_slot == 0 {
+        // Nothing was processed before the disconnect, so there is nothing to reconcile
+        return;
+    }
+
+    let config = config.clone();
+    let endpoint = endpoint.clone();
+    let context = context.clone();
+    tokio::spawn(async move {
+        if let Err(e) = reconcile::reconcile_pipeline(&config, &endpoint, &context, since_slot).await {
+            error!(pipeline = %config.name, endpoint = %endpoint.http_url, error = %e, "❌ Reconciliation failed");
+        }
+    });
+}
+
+// Pool and wallet position indexes only cover Meteora DAMM V2
+pub fn indexes_positions(config: &PipelineConfig) -> bool {
+    config
+        .programs
+        .iter()
+        .any(|program| matches!(program, IndexedProgram::MeteoraDammV2))
+}
+
+// Logs the unchanged updates skipped since the last report and in total, for healthy pipelines too
+async fn report_skipped_updates(pipeline: String, context: ProcessorContext) {
+    let mut interval = tokio::time::interval(DEDUP_REPORT_INTERVAL);
+    // The first tick completes immediately
+    interval.tick().await;
+    let mut reported = 0;
+
+    loop {
+        interval.tick().await;
+        let skipped_updates = context.skipped_updates.load(Ordering::Relaxed);
+        info!(
+            pipeline = %pipeline,
+            skipped_updates = skipped_updates - reported,
+            skipped_total = skipped_updates,
+            interval_secs = DEDUP_REPORT_INTERVAL.as_secs(),
+            "⏭️ Unchanged account updates skipped"
+        );
+        reported = skipped_updates;
+    }
+}
+
+async fn log_reconnect(config: &PipelineConfig, endpoint: &RpcEndpoint, context: &ProcessorContext, attempt: u64) {
+    let last_slot = context.last_slot();
+    let current_slot = RpcClient::new(endpoint.http_url.clone()).get_slot().await.ok();
+    let slot_gap = current_slot.map(|slot| slot.saturating_sub(last_slot));
+    let skipped_updates = context.skipped_updates.load(Ordering::Relaxed);
+
+    info!(
+        pipeline = %config.name,
+        endpoint = %endpoint.ws_url,
+        attempt,
+        last_slot,
+        current_slot = ?current_slot,
+        slot_gap = ?slot_gap,
+        skipped_updates,
+        "🔌 Reconnecting Carbon pipeline"
+    );
+}
@@ -0,0 +1,97 @@
+use serde_json::json;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_pubkey::Pubkey;
+use std::sync::Arc;
+
+use crate::cache::RedisCache;
+use crate::commitment::Commitment;
+use crate::config::DedupConfig;
+use crate::database::{Database, NewAccountUpdate};
+use crate::fanout::Fanout;
+use crate::idl::IdlProgram;
+use crate::mints::MintMetadataService;
+use crate::processor::{ProcessorContext, content_hash};
+use crate::registry::IndexedProgram;
+use crate::valuation::Revaluations;
+
+// Nothing listens here, so RPC calls a test doesn't mock fail fast
+pub const UNREACHABLE_RPC: &str = "http://127.0.0.1:1";
+
+// Processor handles over an in-memory database. Redis is unreachable, so cache writes and
+// fanout publishes fail and are only logged, as they are in production.
+pub async fn processor_context(commitment: Commitment) -> ProcessorContext {
+    let database = Arc::new(Database::in_memory().await.unwrap());
+    let cache = Arc::new(RedisCache::unreachable());
+
+    ProcessorContext {
+        pipeline: "test".to_string(),
+        database: database.clone(),
+        cache: cache.clone(),
+        fanout: Arc::new(Fanout::new("redis://127.0.0.1:1", "test").unwrap()),
+        write_versions: Default::default(),
+        block_groups: None,
+        last_slot: Default::default(),
+        commitment,
+        dedup: DedupConfig {
+            enabled: false,
+            heartbeat: false,
+        },
+        skipped_updates: Default::default(),
+        mint_metadata: Arc::new(MintMetadataService::new(RpcClient::new(UNREACHABLE_RPC.to_string()), database, cache)),
+        revaluations: Arc::new(Revaluations::new(UNREACHABLE_RPC.to_string())),
+        indexed_accounts: Default::default(),
+    }
+}
+
+// An IDL program with a single `Counter { count: u64 }` account
+pub fn counter_program() -> IndexedProgram {
+    let idl = json!({
+        "address": "Counter111111111111111111111111111111111111",
+        "metadata": { "name": "counter" },
+        "accounts": [{ "name": "Counter", "discriminator": [1, 2, 3, 4, 5, 6, 7, 8] }],
+        "types": [{
+            "name": "Counter",
+            "type": { "kind": "struct", "fields": [{ "name": "count", "type": "u64" }] }
+        }]
+    });
+    IndexedProgram::Idl(Arc::new(IdlProgram::from_json(&idl, "idl:test".to_string()).unwrap()))
+}
+
+pub fn counter_data(count: u64) -> Vec<u8> {
+    [&[1, 2, 3, 4, 5, 6, 7, 8][..], &count.to_le_bytes()].concat()
+}
+
+// A Meteora DAMM V2 Position account: discriminator, pool and NFT mint, then zeroed amounts, checkpoints,
+// reward infos and padding up to the program's 408 bytes
+pub fn position_data(pool: &Pubkey, nft_mint: &Pubkey) -> Vec<u8> {
+    let discriminator = IndexedProgram::MeteoraDammV2.account_discriminator("Position").unwrap();
+    let mut data = [&discriminator[..], pool.as_ref(), nft_mint.as_ref()].concat();
+    data.resize(408, 0);
+    data
+}
+
+// A live update as a processor would store it, decoded by the program
+pub fn new_update(program: &IndexedProgram, pubkey: &Pubkey, slot: u64, account: solana_account::Account) -> NewAccountUpdate {
+    let (account_type, data_json) = program.decode_account(&account).unwrap();
+    NewAccountUpdate {
+        pubkey: pubkey.to_string(),
+        slot,
+        program_id: program.program_id().to_string(),
+        account_type,
+        owner: account.owner.to_string(),
+        lamports: account.lamports,
+        data_json,
+        write_version: None,
+        transaction_signature: None,
+        origin: "live".to_string(),
+        commitment: "processed".to_string(),
+        data_hash: content_hash(&account),
+        executable: account.executable,
+        rent_epoch: account.rent_epoch,
+        data: account.data,
+        decoder_version: program.decoder_version().to_string(),
+        event_type: "updated".to_string(),
+        derived: None,
+        mints: None,
+    }
+}
@@ -0,0 +1,447 @@
+use carbon_core::account::AccountDecoder;
+use carbon_meteora_damm_v2_decoder::MeteoraDammV2Decoder;
+use carbon_meteora_damm_v2_decoder::accounts::MeteoraDammV2Account;
+use carbon_meteora_damm_v2_decoder::accounts::pool::Pool;
+use carbon_meteora_damm_v2_decoder::accounts::position::Position;
+use carbon_meteora_damm_v2_decoder::types::RewardInfo;
+use chrono::{DateTime, Utc};
+use primitive_types::{U256, U512};
+use serde::{Deserialize, Serialize};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{warn, debug, instrument};
+
+use crate::database::{AccountUpdate, StoredRawAccount};
+use crate::fanout::FanoutEvent;
+use crate::processor::ProcessorContext;
+
+// sqrt prices, liquidity and reward rates are Q64.64
+const RESOLUTION: usize = 64;
+// Per-liquidity fee and reward accumulators carry 128 fractional bits
+const LIQUIDITY_SCALE: usize = 128;
+const NUM_REWARDS: usize = 2;
+
+// Pool updates arriving within this window are re-valued together
+const REVALUE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+// What a Meteora DAMM V2 position is worth against the current state of its pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PositionValue {
+    pub position: String,
+    pub pool: String,
+    // Slot of the later of the pool and position states valued; rewards are accrued up to its block time
+    pub slot: i64,
+    pub token_a_mint: String,
+    pub token_b_mint: String,
+    // Unlocked, vested and permanently locked liquidity together, as a decimal string since it overflows JSON numbers
+    pub liquidity: String,
+    pub amount_a: u64,
+    pub amount_b: u64,
+    // Unclaimed fees, in token A and token B
+    pub fee_a: u64,
+    pub fee_b: u64,
+    // Pending amounts of the pool's reward mints, None for reward slots that are not initialized
+    pub reward_mints: [Option<String>; NUM_REWARDS],
+    pub rewards: [u64; NUM_REWARDS],
+}
+
+// Pools or positions whose values are stale, re-valued together once updates pause
+pub struct Revaluations {
+    // Block times of the slots values are computed at
+    rpc_client: RpcClient,
+    pending: Mutex<HashMap<String, Stale>>,
+    notify: Notify,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stale {
+    Pool,
+    Position,
+}
+
+impl std::fmt::Debug for Revaluations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Revaluations")
+            .field("pending", &self.pending.lock().unwrap().len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Revaluations {
+    pub fn new(rpc_http_url: String) -> Self {
+        Self {
+            rpc_client: RpcClient::new(rpc_http_url),
+            pending: Mutex::default(),
+            notify: Notify::new(),
+        }
+    }
+
+    fn schedule(&self, pubkey: &str, stale: Stale) {
+        self.pending.lock().unwrap().insert(pubkey.to_string(), stale);
+        self.notify.notify_one();
+    }
+
+    fn cancel(&self, pubkey: &str) {
+        self.pending.lock().unwrap().remove(pubkey);
+    }
+
+    fn take(&self) -> HashMap<String, Stale> {
+        std::mem::take(&mut *self.pending.lock().unwrap())
+    }
+
+    // Put back a batch that failed, unless newer work for the same key arrived meanwhile
+    fn requeue(&self, stale: HashMap<String, Stale>) {
+        let mut pending = self.pending.lock().unwrap();
+        for (pubkey, kind) in stale {
+            pending.entry(pubkey).or_insert(kind);
+        }
+        self.notify.notify_one();
+    }
+}
+
+// Queue the positions a stored Meteora DAMM V2 update affects for re-valuation: the position itself,
+// or every known position of a pool. A closed position's value is dropped right away.
+#[instrument(skip(context, account), fields(pubkey = %account.pubkey, account_type = %account.account_type))]
+pub async fn revalue(context: &ProcessorContext, account: &AccountUpdate) {
+    if account.program_id != carbon_meteora_damm_v2_decoder::PROGRAM_ID.to_string() {
+        return;
+    }
+
+    match account.account_type.as_str() {
+        "Position" if account.is_tombstone() => {
+            context.revaluations.cancel(&account.pubkey);
+            if let Err(e) = context.database.delete_position_value(&account.pubkey).await {
+                warn!(pubkey = %account.pubkey, error = %e, "⚠️ Failed to drop closed position value");
+            }
+        }
+        "Position" => context.revaluations.schedule(&account.pubkey, Stale::Position),
+        "Pool" if !account.is_tombstone() => context.revaluations.schedule(&account.pubkey, Stale::Pool),
+        _ => {}
+    }
+}
+
+// Re-values queued pools and positions in the background, so pool updates don't wait on
+// valuing every position, and a burst of updates to one pool re-values it once
+#[instrument(skip(context), fields(pipeline = %context.pipeline))]
+pub async fn run(context: ProcessorContext) {
+    loop {
+        context.revaluations.notify.notified().await;
+        tokio::time::sleep(REVALUE_DEBOUNCE).await;
+        drain(&context).await;
+    }
+}
+
+// Re-value everything queued so far against the latest stored states
+pub async fn drain(context: &ProcessorContext) {
+    let stale = context.revaluations.take();
+    if stale.is_empty() {
+        return;
+    }
+
+    if let Err(e) = revalue_stale(context, &stale).await {
+        warn!(error = %e, "⚠️ Failed to re-value positions, retrying with the next batch");
+        context.revaluations.requeue(stale);
+    }
+}
+
+async fn revalue_stale(context: &ProcessorContext, stale: &HashMap<String, Stale>) -> Result<(), sqlx::Error> {
+    let mut block_times = HashMap::new();
+    let mut by_pool: HashMap<String, Vec<StoredRawAccount>> = HashMap::new();
+
+    let pools: Vec<&String> = stale.iter().filter(|(_, kind)| **kind == Stale::Pool).map(|(key, _)| key).collect();
+    for pool in &pools {
+        let positions = context.database.get_pool_positions(pool).await?;
+        if !positions.is_empty() {
+            by_pool.insert((*pool).clone(), context.database.get_latest_raw_accounts(&positions).await?);
+        }
+    }
+
+    // Positions that changed on their own, unless their pool is being re-valued anyway
+    let positions: Vec<String> = stale
+        .iter()
+        .filter(|(_, kind)| **kind == Stale::Position)
+        .map(|(key, _)| key.clone())
+        .collect();
+    for row in context.database.get_latest_raw_accounts(&positions).await? {
+        let Some(position) = row.to_account().and_then(|raw| decode_position(&raw)) else {
+            continue;
+        };
+        let pool = position.pool.to_string();
+        if !stale.contains_key(&pool) {
+            by_pool.entry(pool).or_default().push(row);
+        }
+    }
+
+    for (pool_key, positions) in by_pool {
+        let pool_row = context
+            .database
+            .get_latest_raw_accounts(std::slice::from_ref(&pool_key))
+            .await?
+            .pop();
+        let pool = pool_row.as_ref().and_then(|row| {
+            let pool = decode_pool(&row.to_account()?)?;
+            Some((row_slot_and_time(row), pool))
+        });
+        debug!(pool = %pool_key, position_count = positions.len(), "🧮 Re-valuing pool positions");
+
+        for row in positions {
+            let Some(position) = row.to_account().and_then(|raw| decode_position(&raw)) else {
+                continue;
+            };
+            let Some(((pool_slot, pool_stored_at), pool)) = &pool else {
+                debug!(position = %row.pubkey, pool = %pool_key, "⏳ Pool not stored yet, valuing position once it is");
+                store_and_publish(context, &row.pubkey, &pool_key, row.slot, None).await?;
+                continue;
+            };
+
+            // The value holds as of the later of the two states
+            let (slot, stored_at) = (*pool_slot, *pool_stored_at).max(row_slot_and_time(&row));
+            let now = block_time(context, slot, stored_at, &mut block_times).await;
+            let value = value_position(&row.pubkey, &position, &pool_key, pool, slot, now);
+            store_and_publish(context, &row.pubkey, &pool_key, slot, Some(value)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn row_slot_and_time(row: &StoredRawAccount) -> (i64, DateTime<Utc>) {
+    (row.slot, row.created_at)
+}
+
+// Unix time of the slot's block, or of when the update was stored if the node can't tell
+async fn block_time(
+    context: &ProcessorContext,
+    slot: i64,
+    stored_at: DateTime<Utc>,
+    block_times: &mut HashMap<i64, i64>,
+) -> u64 {
+    if let Some(&timestamp) = block_times.get(&slot) {
+        return timestamp.max(0) as u64;
+    }
+
+    let timestamp = match context.revaluations.rpc_client.get_block_time(slot as u64).await {
+        Ok(timestamp) => timestamp,
+        Err(e) => {
+            debug!(slot, error = %e, "🔍 Block time unavailable, using the time the update was stored");
+            stored_at.timestamp()
+        }
+    };
+    block_times.insert(slot, timestamp);
+    timestamp.max(0) as u64
+}
+
+// Unchanged values are stored but not published again
+async fn store_and_publish(
+    context: &ProcessorContext,
+    position: &str,
+    pool: &str,
+    slot: i64,
+    value: Option<PositionValue>,
+) -> Result<(), sqlx::Error> {
+    let changed = context
+        .database
+        .upsert_position_value(position, pool, slot as u64, value.as_ref())
+        .await?;
+
+    if let (true, Some(value)) = (changed, value) {
+        debug!(position = %position, amount_a = value.amount_a, amount_b = value.amount_b, "📡 Publishing position value");
+        if let Err(e) = context.fanout.publish(&FanoutEvent::PositionValue(value)).await {
+            warn!(position = %position, error = %e, "⚠️ Failed to publish position value to gateways");
+        }
+    }
+
+    Ok(())
+}
+
+pub fn decode_pool(account: &solana_account::Account) -> Option<Pool> {
+    match MeteoraDammV2Decoder.decode_account(account)?.data {
+        MeteoraDammV2Account::Pool(pool) => Some(pool),
+        _ => None,
+    }
+}
+
+pub fn decode_position(account: &solana_account::Account) -> Option<Position> {
+    match MeteoraDammV2Decoder.decode_account(account)?.data {
+        MeteoraDammV2Account::Position(position) => Some(position),
+        _ => None,
+    }
+}
+
+// `now` is the Unix time rewards are accrued up to
+fn value_position(position_key: &str, position: &Position, pool_key: &str, pool: &Pool, slot: i64, now: u64) -> PositionValue {
+    let liquidity = position
+        .unlocked_liquidity
+        .saturating_add(position.vested_liquidity)
+        .saturating_add(position.permanent_locked_liquidity);
+
+    let mut reward_mints: [Option<String>; NUM_REWARDS] = Default::default();
+    let mut rewards = [0; NUM_REWARDS];
+    for (index, info) in pool.reward_infos.iter().enumerate().take(NUM_REWARDS) {
+        if info.initialized == 0 {
+            continue;
+        }
+        let user = &position.reward_infos[index];
+        let stored = reward_per_token_stored(info, pool.liquidity, now);
+        reward_mints[index] = Some(info.mint.to_string());
+        rewards[index] = user
+            .reward_pendings
+            .saturating_add(accrued(liquidity, stored, &user.reward_per_token_checkpoint));
+    }
+
+    PositionValue {
+        position: position_key.to_string(),
+        pool: pool_key.to_string(),
+        slot,
+        token_a_mint: pool.token_a_mint.to_string(),
+        token_b_mint: pool.token_b_mint.to_string(),
+        liquidity: liquidity.to_string(),
+        amount_a: amount_a(liquidity, pool.sqrt_price, pool.sqrt_max_price),
+        amount_b: amount_b(liquidity, pool.sqrt_min_price, pool.sqrt_price),
+        fee_a: position.fee_a_pending.saturating_add(accrued(
+            liquidity,
+            U256::from_little_endian(&pool.fee_a_per_liquidity),
+            &position.fee_a_per_token_checkpoint,
+        )),
+        fee_b: position.fee_b_pending.saturating_add(accrued(
+            liquidity,
+            U256::from_little_endian(&pool.fee_b_per_liquidity),
+            &position.fee_b_per_token_checkpoint,
+        )),
+        reward_mints,
+        rewards,
+    }
+}
+
+// Token A held between the current price and the upper bound: L * (upper - p) / (p * upper), rounded down
+fn amount_a(liquidity: u128, sqrt_price: u128, sqrt_max_price: u128) -> u64 {
+    if sqrt_price == 0 || sqrt_price >= sqrt_max_price {
+        return 0;
+    }
+    let numerator = U256::from(liquidity).full_mul(U256::from(sqrt_max_price - sqrt_price));
+    let denominator = U256::from(sqrt_price).full_mul(U256::from(sqrt_max_price));
+    to_u64(numerator / denominator)
+}
+
+// Token B held between the lower bound and the current price: L * (p - lower), rounded down
+fn amount_b(liquidity: u128, sqrt_min_price: u128, sqrt_price: u128) -> u64 {
+    if sqrt_price <= sqrt_min_price {
+        return 0;
+    }
+    to_u64(U256::from(liquidity).full_mul(U256::from(sqrt_price - sqrt_min_price)) >> (RESOLUTION * 2))
+}
+
+// Earned by `liquidity` since its checkpoint of a per-liquidity accumulator
+fn accrued(liquidity: u128, current: U256, checkpoint: &[u8; 32]) -> u64 {
+    let delta = current.saturating_sub(U256::from_little_endian(checkpoint));
+    to_u64(U256::from(liquidity).full_mul(delta) >> LIQUIDITY_SCALE)
+}
+
+// The pool's reward accumulator brought forward to `now`, as the program does before paying out
+fn reward_per_token_stored(info: &RewardInfo, pool_liquidity: u128, now: u64) -> U256 {
+    accumulate_reward(
+        U256::from_little_endian(&info.reward_per_token_stored),
+        info.reward_rate,
+        info.last_update_time,
+        info.reward_duration_end,
+        pool_liquidity,
+        now,
+    )
+}
+
+fn accumulate_reward(stored: U256, reward_rate: u128, last_update_time: u64, reward_duration_end: u64, pool_liquidity: u128, now: u64) -> U256 {
+    let elapsed = now.min(reward_duration_end).saturating_sub(last_update_time);
+    if pool_liquidity == 0 || elapsed == 0 {
+        return stored;
+    }
+
+    // reward_rate * elapsed is Q64.64 tokens; spread over Q64.64 liquidity at the accumulator's scale
+    let total = U256::from(reward_rate).full_mul(U256::from(elapsed)) << (LIQUIDITY_SCALE - RESOLUTION);
+    let per_liquidity = U256::try_from(total / U512::from(pool_liquidity)).unwrap_or(U256::MAX);
+    stored.saturating_add(per_liquidity)
+}
+
+fn to_u64(value: U512) -> u64 {
+    if value > U512::from(u64::MAX) { u64::MAX } else { value.low_u64() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ONE: u128 = 1 << RESOLUTION;
+
+    fn le_bytes(value: U256) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        value.to_little_endian(&mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn amount_a_spans_current_price_to_upper_bound() {
+        // L * (2 - 1) / (1 * 2) at sqrt prices 1 and 2
+        assert_eq!(amount_a(1000 * ONE, ONE, 2 * ONE), 500);
+        // 500.5 rounds down
+        assert_eq!(amount_a(1001 * ONE, ONE, 2 * ONE), 500);
+    }
+
+    #[test]
+    fn amount_a_is_zero_at_or_above_upper_bound() {
+        assert_eq!(amount_a(1000 * ONE, 2 * ONE, 2 * ONE), 0);
+        assert_eq!(amount_a(1000 * ONE, 3 * ONE, 2 * ONE), 0);
+        assert_eq!(amount_a(1000 * ONE, 0, 2 * ONE), 0);
+    }
+
+    #[test]
+    fn amount_b_spans_lower_bound_to_current_price() {
+        // L * (2 - 1) at sqrt prices 1 and 2
+        assert_eq!(amount_b(1000 * ONE, ONE, 2 * ONE), 1000);
+        // L * (1.5 - 1) = 500
+        assert_eq!(amount_b(1000 * ONE, ONE, ONE + ONE / 2), 500);
+        assert_eq!(amount_b(1000 * ONE, 2 * ONE, 2 * ONE), 0);
+    }
+
+    #[test]
+    fn amounts_saturate_instead_of_wrapping() {
+        assert_eq!(amount_b(u128::MAX, 0, 4 * ONE), u64::MAX);
+        assert_eq!(to_u64(U512::from(u64::MAX) + U512::one()), u64::MAX);
+        assert_eq!(to_u64(U512::from(42u64)), 42);
+    }
+
+    #[test]
+    fn accrued_scales_accumulator_delta_by_liquidity() {
+        let checkpoint = le_bytes(U256::from(ONE));
+        // Liquidity 10, accumulator up by 3 per unit of liquidity
+        assert_eq!(accrued(10 * ONE, U256::from(4 * ONE), &checkpoint), 30);
+        // A checkpoint ahead of the accumulator earns nothing
+        assert_eq!(accrued(10 * ONE, U256::zero(), &checkpoint), 0);
+    }
+
+    #[test]
+    fn rewards_accumulate_over_elapsed_time() {
+        // 5 tokens a second for 100 seconds over liquidity 10: 50 per unit of liquidity
+        let stored = accumulate_reward(U256::zero(), 5 * ONE, 1_000, 10_000, 10 * ONE, 1_100);
+        assert_eq!(stored, U256::from(50u8) << RESOLUTION);
+        // Sole provider gets the whole 500
+        assert_eq!(accrued(10 * ONE, stored, &[0u8; 32]), 500);
+    }
+
+    #[test]
+    fn rewards_stop_at_the_end_of_the_reward_duration() {
+        let until_end = accumulate_reward(U256::zero(), 5 * ONE, 1_000, 1_100, 10 * ONE, 1_100);
+        let long_after = accumulate_reward(U256::zero(), 5 * ONE, 1_000, 1_100, 10 * ONE, 99_999);
+        assert_eq!(until_end, long_after);
+    }
+
+    #[test]
+    fn rewards_do_not_accumulate_without_liquidity_or_time() {
+        let stored = U256::from(7u8);
+        assert_eq!(accumulate_reward(stored, 5 * ONE, 1_000, 10_000, 0, 2_000), stored);
+        assert_eq!(accumulate_reward(stored, 5 * ONE, 1_000, 10_000, 10 * ONE, 1_000), stored);
+        // A block time before the last update must not underflow
+        assert_eq!(accumulate_reward(stored, 5 * ONE, 1_000, 10_000, 10 * ONE, 900), stored);
+    }
+}
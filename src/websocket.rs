@@ -1,3 +1,4 @@
+use base64::prelude::{BASE64_STANDARD, Engine};
 use futures_util::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -6,28 +7,116 @@ use tokio::sync::{RwLock, broadcast};
 use warp::{Filter, ws::{Message, WebSocket}};
 use tracing::{info, warn, error, debug, instrument};
 
+use crate::block_subscribe::BlockEvent;
 use crate::cache::RedisCache;
-use crate::database::{AccountUpdate, Database};
+use crate::commitment::{CommitmentUpgrade, Rollback};
+use crate::database::{AccountUpdate, Database, InstructionEvent};
+use crate::mints::PoolMints;
+use crate::portfolio::WalletPositionEvent;
+use crate::positions::PoolPositionEvent;
+use crate::pricing::DerivedPrice;
+use crate::registry::NewPool;
+use crate::valuation::PositionValue;
 
 pub type ClientId = u64;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SubscriptionRequest {
-    pub action: String, // "subscribe" or "unsubscribe"
-    pub pubkey: String,
+    pub action: String, // "subscribe", "unsubscribe", "subscribe_trades", "unsubscribe_trades", "subscribe_blocks", "unsubscribe_blocks", "subscribe_new_pools", "unsubscribe_new_pools", "subscribe_pool_positions", "unsubscribe_pool_positions", "subscribe_wallet" or "unsubscribe_wallet"
+    pub pubkey: String, // account pubkey, pool pubkey for trades and pool positions, wallet address, or program id for blocks and new pools
+    // Raw account data to include in account updates, as in Solana RPC
+    #[serde(default)]
+    pub encoding: Option<DataEncoding>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DataEncoding {
+    #[serde(rename = "base64")]
+    Base64,
+    #[serde(rename = "base64+zstd")]
+    Base64Zstd,
+}
+
+// Everything sent to clients, tagged with its `type`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    AccountUpdate(AccountUpdateMessage),
+    Trade(TradeMessage),
+    Block(BlockEvent),
+    CommitmentUpgrade(CommitmentUpgrade),
+    Rollback(Rollback),
+    Closed(ClosedMessage),
+    NewPool(NewPool),
+    PositionValue(PositionValue),
+    PoolPositions(PoolPositionsMessage),
+    PoolPosition(PoolPositionEvent),
+    WalletPositions(WalletPositionsMessage),
+    WalletPosition(WalletPositionEvent),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AccountUpdateMessage {
     pub pubkey: String,
     pub account: AccountUpdate,
-    pub source: String, // "cache" or "database"
+    pub source: String, // "cache", "database", "realtime" or "reconciled"
+    // `[data, encoding]` when the subscription asked for raw data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<[String; 2]>,
+    // Values computed from the account, such as a pool's spot prices
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived: Option<DerivedPrice>,
+    // Decimals, supply, token program, name and symbol of a pool's mints
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mints: Option<PoolMints>,
+}
+
+// Sent instead of an account update once the account was closed or reassigned to another program
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosedMessage {
+    pub pubkey: String,
+    pub slot: i64,
+    pub reason: String, // "closed" or "owner_changed"
+    pub owner: String,
+    // The tombstone row, with a null data_json
+    pub account: AccountUpdate,
+    pub source: String,
+}
+
+// The open positions of a pool, sent when a client subscribes to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolPositionsMessage {
+    pub pool: String,
+    pub positions: Vec<AccountUpdate>,
+    pub source: String, // "cache" when the set came from Redis, "database" otherwise
+}
+
+// The positions a wallet holds, sent when a client subscribes to it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalletPositionsMessage {
+    pub wallet: String,
+    pub positions: Vec<AccountUpdate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TradeMessage {
+    pub pool: String,
+    pub event: InstructionEvent,
 }
 
 #[derive(Debug, Clone)]
 pub struct WebSocketServer {
-    clients: Arc<RwLock<HashMap<ClientId, broadcast::Sender<AccountUpdateMessage>>>>,
+    clients: Arc<RwLock<HashMap<ClientId, broadcast::Sender<ServerMessage>>>>,
     subscriptions: Arc<RwLock<HashMap<String, Vec<ClientId>>>>,
+    // Raw data encoding requested per (client, pubkey) account subscription
+    encodings: Arc<RwLock<HashMap<(ClientId, String), DataEncoding>>>,
+    trade_subscriptions: Arc<RwLock<HashMap<String, Vec<ClientId>>>>,
+    block_subscriptions: Arc<RwLock<HashMap<String, Vec<ClientId>>>>,
+    new_pool_subscriptions: Arc<RwLock<HashMap<String, Vec<ClientId>>>>,
+    pool_position_subscriptions: Arc<RwLock<HashMap<String, Vec<ClientId>>>>,
+    wallet_subscriptions: Arc<RwLock<HashMap<String, Vec<ClientId>>>>,
+    // Position -> holding wallet, kept only for wallets someone subscribed to
+    wallet_holdings: Arc<RwLock<HashMap<String, String>>>,
     database: Arc<Database>,
     cache: Arc<RedisCache>,
     next_client_id: Arc<RwLock<u64>>,
@@ -38,6 +127,13 @@ impl WebSocketServer {
         Self {
             clients: Arc::new(RwLock::new(HashMap::new())),
             subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            encodings: Arc::new(RwLock::new(HashMap::new())),
+            trade_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            block_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            new_pool_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            pool_position_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            wallet_subscriptions: Arc::new(RwLock::new(HashMap::new())),
+            wallet_holdings: Arc::new(RwLock::new(HashMap::new())),
             database,
             cache,
             next_client_id: Arc::new(RwLock::new(1)),
@@ -139,26 +235,20 @@ impl WebSocketServer {
         let outgoing_task = tokio::spawn(async move {
             debug!(client_id, "🔄 Starting outgoing message handler for client");
 
-            while let Ok(update) = broadcast_rx.recv().await {
-                debug!(
-                    client_id,
-                    pubkey = %update.pubkey,
-                    account_type = %update.account.account_type,
-                    source = %update.source,
-                    "📡 Broadcasting account update to client"
-                );
+            while let Ok(message) = broadcast_rx.recv().await {
+                debug!(client_id, "📡 Broadcasting message to client");
 
-                match serde_json::to_string(&update) {
+                match serde_json::to_string(&message) {
                     Ok(json) => {
                         if let Err(e) = ws_sender.send(Message::text(json)).await {
                             warn!(client_id, error = %e, "❌ Failed to send update to client");
                             break;
                         } else {
-                            debug!(client_id, pubkey = %update.pubkey, "✅ Account update sent successfully");
+                            debug!(client_id, "✅ Message sent successfully");
                         }
                     }
                     Err(e) => {
-                        error!(client_id, error = %e, pubkey = %update.pubkey, "❌ Failed to serialize update");
+                        error!(client_id, error = %e, "❌ Failed to serialize message");
                     }
                 }
             }
@@ -197,24 +287,32 @@ impl WebSocketServer {
                         .or_insert_with(Vec::new)
                         .push(client_id);
                 }
+                {
+                    let mut encodings = self.encodings.write().await;
+                    match request.encoding {
+                        Some(encoding) => encodings.insert((client_id, request.pubkey.clone()), encoding),
+                        None => encodings.remove(&(client_id, request.pubkey.clone())),
+                    };
+                }
 
                 // Send current account state immediately
                 debug!(client_id, pubkey = %request.pubkey, "🔍 Fetching current account state for new subscription");
                 if let Some((account, source)) = self.get_account_data(&request.pubkey).await {
-                    let message = AccountUpdateMessage {
-                        pubkey: request.pubkey.clone(),
-                        account,
-                        source,
-                    };
-
                     info!(
                         client_id,
                         pubkey = %request.pubkey,
-                        source = %message.source,
-                        account_type = %message.account.account_type,
+                        source = %source,
+                        account_type = %account.account_type,
+                        event_type = %account.event_type,
                         "📤 Sending current account state to new subscriber"
                     );
 
+                    let message = if account.is_tombstone() {
+                        ServerMessage::Closed(closed_message(&request.pubkey, &account, source))
+                    } else {
+                        ServerMessage::AccountUpdate(account_update_message(&request.pubkey, &account, source, request.encoding))
+                    };
+
                     // Send to this specific client
                     let clients = self.clients.read().await;
                     if let Some(tx) = clients.get(&client_id) {
@@ -233,6 +331,8 @@ impl WebSocketServer {
                     "📝 Client unsubscribing from account updates"
                 );
 
+                self.encodings.write().await.remove(&(client_id, request.pubkey.clone()));
+
                 // Remove client from subscription list
                 let mut subs = self.subscriptions.write().await;
                 if let Some(client_list) = subs.get_mut(&request.pubkey) {
@@ -244,6 +344,122 @@ impl WebSocketServer {
                     }
                 }
             }
+            "subscribe_trades" => {
+                info!(
+                    client_id,
+                    pool = %request.pubkey,
+                    "📝 Client subscribing to pool trades"
+                );
+                add_subscriber(&self.trade_subscriptions, &request.pubkey, client_id).await;
+            }
+            "unsubscribe_trades" => {
+                info!(
+                    client_id,
+                    pool = %request.pubkey,
+                    "📝 Client unsubscribing from pool trades"
+                );
+                remove_subscriber(&self.trade_subscriptions, &request.pubkey, client_id).await;
+            }
+            "subscribe_blocks" => {
+                info!(
+                    client_id,
+                    program_id = %request.pubkey,
+                    "📝 Client subscribing to block updates"
+                );
+                add_subscriber(&self.block_subscriptions, &request.pubkey, client_id).await;
+            }
+            "unsubscribe_blocks" => {
+                info!(
+                    client_id,
+                    program_id = %request.pubkey,
+                    "📝 Client unsubscribing from block updates"
+                );
+                remove_subscriber(&self.block_subscriptions, &request.pubkey, client_id).await;
+            }
+            "subscribe_new_pools" => {
+                info!(
+                    client_id,
+                    program_id = %request.pubkey,
+                    "📝 Client subscribing to new pools"
+                );
+                add_subscriber(&self.new_pool_subscriptions, &request.pubkey, client_id).await;
+            }
+            "unsubscribe_new_pools" => {
+                info!(
+                    client_id,
+                    program_id = %request.pubkey,
+                    "📝 Client unsubscribing from new pools"
+                );
+                remove_subscriber(&self.new_pool_subscriptions, &request.pubkey, client_id).await;
+            }
+            "subscribe_pool_positions" => {
+                info!(
+                    client_id,
+                    pool = %request.pubkey,
+                    "📝 Client subscribing to pool positions"
+                );
+                add_subscriber(&self.pool_position_subscriptions, &request.pubkey, client_id).await;
+
+                // Send the pool's current positions immediately
+                if let Some(message) = self.get_pool_positions(&request.pubkey).await {
+                    info!(
+                        client_id,
+                        pool = %request.pubkey,
+                        position_count = message.positions.len(),
+                        source = %message.source,
+                        "📤 Sending current pool positions to new subscriber"
+                    );
+
+                    let clients = self.clients.read().await;
+                    if let Some(tx) = clients.get(&client_id) {
+                        if tx.send(ServerMessage::PoolPositions(message)).is_err() {
+                            warn!(client_id, "⚠️ Failed to send initial pool positions - client may have disconnected");
+                        }
+                    }
+                }
+            }
+            "unsubscribe_pool_positions" => {
+                info!(
+                    client_id,
+                    pool = %request.pubkey,
+                    "📝 Client unsubscribing from pool positions"
+                );
+                remove_subscriber(&self.pool_position_subscriptions, &request.pubkey, client_id).await;
+            }
+            "subscribe_wallet" => {
+                info!(
+                    client_id,
+                    wallet = %request.pubkey,
+                    "📝 Client subscribing to wallet positions"
+                );
+                add_subscriber(&self.wallet_subscriptions, &request.pubkey, client_id).await;
+
+                // Send the positions the wallet holds right now
+                if let Some(message) = self.get_wallet_positions(&request.pubkey).await {
+                    info!(
+                        client_id,
+                        wallet = %request.pubkey,
+                        position_count = message.positions.len(),
+                        "📤 Sending current wallet positions to new subscriber"
+                    );
+
+                    let clients = self.clients.read().await;
+                    if let Some(tx) = clients.get(&client_id) {
+                        if tx.send(ServerMessage::WalletPositions(message)).is_err() {
+                            warn!(client_id, "⚠️ Failed to send initial wallet positions - client may have disconnected");
+                        }
+                    }
+                }
+            }
+            "unsubscribe_wallet" => {
+                info!(
+                    client_id,
+                    wallet = %request.pubkey,
+                    "📝 Client unsubscribing from wallet positions"
+                );
+                remove_subscriber(&self.wallet_subscriptions, &request.pubkey, client_id).await;
+                self.prune_wallet_holdings().await;
+            }
             _ => {
                 warn!(
                     client_id,
@@ -266,27 +482,264 @@ impl WebSocketServer {
                 "📡 Broadcasting account update to subscribed clients"
             );
 
-            let message = AccountUpdateMessage {
-                pubkey: pubkey.to_string(),
-                account: account.clone(),
-                source: "realtime".to_string(),
-            };
+            // Synthetic updates from reconciliation are flagged so clients know they filled a gap
+            let source = if account.origin == "reconciled" { "reconciled" } else { "realtime" };
+
+            // Built once per encoding, since compressing the data isn't free
+            let mut clients_by_encoding: HashMap<Option<DataEncoding>, Vec<ClientId>> = HashMap::new();
+            let encodings = self.encodings.read().await;
+            for &client_id in client_ids {
+                let encoding = encodings.get(&(client_id, pubkey.to_string())).copied();
+                clients_by_encoding.entry(encoding).or_default().push(client_id);
+            }
 
             let clients = self.clients.read().await;
+            for (encoding, client_ids) in clients_by_encoding {
+                let message = if account.is_tombstone() {
+                    ServerMessage::Closed(closed_message(pubkey, account, source.to_string()))
+                } else {
+                    ServerMessage::AccountUpdate(account_update_message(pubkey, account, source.to_string(), encoding))
+                };
+                send_to_clients(&clients, &client_ids, &message);
+            }
+        }
+    }
 
-            for &client_id in client_ids {
-                if let Some(tx) = clients.get(&client_id) {
-                    if tx.send(message.clone()).is_err() {
-                        // Client's receiver is dropped (client disconnected)
-                        warn!(client_id, "⚠️ Client appears to be disconnected during broadcast");
-                    } else {
-                        debug!(client_id, pubkey = %pubkey, "✅ Account update sent to client");
+    #[instrument(skip(self, event), fields(signature = %event.signature, instruction_type = %event.instruction_type))]
+    pub async fn broadcast_trade(&self, event: &InstructionEvent) {
+        let Some(pool) = event.pool.as_deref() else {
+            return;
+        };
+
+        let subs = self.trade_subscriptions.read().await;
+
+        if let Some(client_ids) = subs.get(pool) {
+            info!(
+                pool = %pool,
+                client_count = client_ids.len(),
+                instruction_type = %event.instruction_type,
+                "📡 Broadcasting trade to subscribed clients"
+            );
+
+            let message = ServerMessage::Trade(TradeMessage {
+                pool: pool.to_string(),
+                event: event.clone(),
+            });
+
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, client_ids, &message);
+        }
+    }
+
+    #[instrument(skip(self, upgrade), fields(pubkey = %upgrade.pubkey, slot = upgrade.slot))]
+    pub async fn broadcast_commitment_upgrade(&self, upgrade: &CommitmentUpgrade) {
+        let subs = self.subscriptions.read().await;
+
+        if let Some(client_ids) = subs.get(&upgrade.pubkey) {
+            debug!(
+                pubkey = %upgrade.pubkey,
+                commitment = %upgrade.commitment,
+                client_count = client_ids.len(),
+                "📡 Broadcasting commitment upgrade to subscribed clients"
+            );
+
+            let message = ServerMessage::CommitmentUpgrade(upgrade.clone());
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, client_ids, &message);
+        }
+    }
+
+    #[instrument(skip(self, rollback), fields(pubkey = %rollback.pubkey, slot = rollback.slot))]
+    pub async fn broadcast_rollback(&self, rollback: &Rollback) {
+        let mut client_ids: Vec<ClientId> = self
+            .subscriptions
+            .read()
+            .await
+            .get(&rollback.pubkey)
+            .cloned()
+            .unwrap_or_default();
+        // A pool announced from the dead fork is retracted on the new pools channel too
+        if rollback.new_pool {
+            if let Some(new_pool_clients) = self.new_pool_subscriptions.read().await.get(&rollback.program_id) {
+                for &client_id in new_pool_clients {
+                    if !client_ids.contains(&client_id) {
+                        client_ids.push(client_id);
                     }
-                } else {
-                    warn!(client_id, "⚠️ Client not found in clients map during broadcast");
                 }
             }
         }
+
+        if !client_ids.is_empty() {
+            info!(
+                pubkey = %rollback.pubkey,
+                slot = rollback.slot,
+                client_count = client_ids.len(),
+                "📡 Broadcasting rollback to subscribed clients"
+            );
+
+            let message = ServerMessage::Rollback(Rollback {
+                account: rollback.account.as_ref().map(AccountUpdate::without_raw_data),
+                ..rollback.clone()
+            });
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, &client_ids, &message);
+        }
+    }
+
+    #[instrument(skip(self, new_pool), fields(pool = %new_pool.pool, program_id = %new_pool.program_id))]
+    pub async fn broadcast_new_pool(&self, new_pool: &NewPool) {
+        let subs = self.new_pool_subscriptions.read().await;
+
+        if let Some(client_ids) = subs.get(&new_pool.program_id) {
+            info!(
+                pool = %new_pool.pool,
+                client_count = client_ids.len(),
+                "📡 Broadcasting new pool to subscribed clients"
+            );
+
+            let message = ServerMessage::NewPool(new_pool.clone());
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, client_ids, &message);
+        }
+    }
+
+    #[instrument(skip(self, event), fields(pool = %event.pool, position = %event.position, change = %event.change))]
+    pub async fn broadcast_pool_position(&self, event: &PoolPositionEvent) {
+        let subs = self.pool_position_subscriptions.read().await;
+        let mut client_ids: Vec<ClientId> = subs.get(&event.pool).cloned().unwrap_or_default();
+        // Holders hear about closures through their wallet_position message instead
+        if event.change == "changed" {
+            client_ids.extend(self.wallet_subscribers(&event.position).await);
+        }
+        client_ids.sort_unstable();
+        client_ids.dedup();
+
+        if !client_ids.is_empty() {
+            debug!(
+                pool = %event.pool,
+                position = %event.position,
+                client_count = client_ids.len(),
+                "📡 Broadcasting pool position to subscribed clients"
+            );
+
+            let message = ServerMessage::PoolPosition(event.clone());
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, &client_ids, &message);
+        }
+    }
+
+    // Position values go to the position's account subscribers, next to its account updates,
+    // and to subscribers of its pool's positions or of the wallet holding it
+    #[instrument(skip(self, value), fields(position = %value.position, slot = value.slot))]
+    pub async fn broadcast_position_value(&self, value: &PositionValue) {
+        let subs = self.subscriptions.read().await;
+        let pool_subs = self.pool_position_subscriptions.read().await;
+        let mut client_ids: Vec<ClientId> = subs.get(&value.position).cloned().unwrap_or_default();
+        client_ids.extend(pool_subs.get(&value.pool).into_iter().flatten());
+        client_ids.extend(self.wallet_subscribers(&value.position).await);
+        client_ids.sort_unstable();
+        client_ids.dedup();
+
+        if !client_ids.is_empty() {
+            debug!(
+                position = %value.position,
+                client_count = client_ids.len(),
+                "📡 Broadcasting position value to subscribed clients"
+            );
+
+            let message = ServerMessage::PositionValue(value.clone());
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, &client_ids, &message);
+        }
+    }
+
+    // Keeps the holdings of subscribed wallets current before telling their subscribers
+    #[instrument(skip(self, event), fields(wallet = %event.wallet, position = %event.position, change = %event.change))]
+    pub async fn broadcast_wallet_position(&self, event: &WalletPositionEvent) {
+        let subs = self.wallet_subscriptions.read().await;
+
+        if let Some(client_ids) = subs.get(&event.wallet) {
+            {
+                let mut holdings = self.wallet_holdings.write().await;
+                if event.change == "added" {
+                    holdings.insert(event.position.clone(), event.wallet.clone());
+                } else if holdings.get(&event.position) == Some(&event.wallet) {
+                    holdings.remove(&event.position);
+                }
+            }
+
+            info!(
+                wallet = %event.wallet,
+                position = %event.position,
+                client_count = client_ids.len(),
+                "📡 Broadcasting wallet position to subscribed clients"
+            );
+
+            let message = ServerMessage::WalletPosition(event.clone());
+            let clients = self.clients.read().await;
+
+            send_to_clients(&clients, client_ids, &message);
+        }
+    }
+
+    // Subscribers of the wallet holding the position, if anyone follows that wallet
+    async fn wallet_subscribers(&self, position: &str) -> Vec<ClientId> {
+        let Some(wallet) = self.wallet_holdings.read().await.get(position).cloned() else {
+            return Vec::new();
+        };
+        self.wallet_subscriptions.read().await.get(&wallet).cloned().unwrap_or_default()
+    }
+
+    // Forget the holdings of wallets nobody follows anymore
+    async fn prune_wallet_holdings(&self) {
+        let subs = self.wallet_subscriptions.read().await;
+        self.wallet_holdings.write().await.retain(|_, wallet| subs.contains_key(wallet));
+    }
+
+    // Each subscriber gets only its program's share of the block
+    #[instrument(skip(self, block), fields(slot = block.slot))]
+    pub async fn broadcast_block(&self, block: &BlockEvent) {
+        let subs = self.block_subscriptions.read().await;
+        let clients = self.clients.read().await;
+
+        for (program_id, client_ids) in subs.iter() {
+            let accounts: Vec<_> = block
+                .accounts
+                .iter()
+                .filter(|account| &account.program_id == program_id)
+                .map(AccountUpdate::without_raw_data)
+                .collect();
+            let instructions: Vec<_> = block
+                .instructions
+                .iter()
+                .filter(|event| &event.program_id == program_id)
+                .cloned()
+                .collect();
+            if accounts.is_empty() && instructions.is_empty() {
+                continue;
+            }
+
+            info!(
+                program_id = %program_id,
+                slot = block.slot,
+                client_count = client_ids.len(),
+                "📡 Broadcasting block update to subscribed clients"
+            );
+
+            let message = ServerMessage::Block(BlockEvent {
+                accounts,
+                instructions,
+                ..block.clone()
+            });
+
+            send_to_clients(&clients, client_ids, &message);
+        }
     }
 
     #[instrument(skip(self), fields(pubkey = %pubkey))]
@@ -302,7 +755,7 @@ impl WebSocketServer {
         debug!(pubkey = %pubkey, "🔍 Account not in cache, checking database");
         if let Ok(Some(account)) = self.database.get_latest_account_state(pubkey).await {
             info!(pubkey = %pubkey, account_type = %account.account_type, "✅ Account retrieved from database, caching for future requests");
-            // Cache the result for next time
+            // Cache the result for next time; a slot-aware write, so a slow read can't replace a fresher live update
             if let Err(e) = self.cache.set_account(pubkey, &account).await {
                 warn!(pubkey = %pubkey, error = %e, "⚠️ Failed to cache account after database retrieval");
             }
@@ -313,6 +766,85 @@ impl WebSocketServer {
         None
     }
 
+    // Positions from the Redis set once it is seeded, otherwise from SQLite, which also seeds the set
+    #[instrument(skip(self), fields(pool = %pool))]
+    async fn get_pool_positions(&self, pool: &str) -> Option<PoolPositionsMessage> {
+        let cached = match self.cache.get_pool_positions(pool).await {
+            Ok(cached) => cached,
+            Err(e) => {
+                warn!(pool = %pool, error = %e, "⚠️ Failed to read pool positions from Redis");
+                None
+            }
+        };
+
+        let (positions, source) = match cached {
+            Some(positions) => (positions, "cache"),
+            None => {
+                let positions = match self.database.get_pool_positions(pool).await {
+                    Ok(positions) => positions,
+                    Err(e) => {
+                        error!(pool = %pool, error = %e, "❌ Failed to read pool positions from database");
+                        return None;
+                    }
+                };
+                if let Err(e) = self.cache.seed_pool_positions(pool, &positions).await {
+                    warn!(pool = %pool, error = %e, "⚠️ Failed to seed Redis pool positions after database retrieval");
+                }
+                (positions, "database")
+            }
+        };
+
+        let mut accounts = match self.database.get_latest_account_states(&positions).await {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                error!(pool = %pool, error = %e, "❌ Failed to read pool position states from database");
+                return None;
+            }
+        };
+        accounts.retain(|account| !account.is_tombstone());
+        accounts.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+
+        Some(PoolPositionsMessage {
+            pool: pool.to_string(),
+            positions: accounts.iter().map(AccountUpdate::without_raw_data).collect(),
+            source: source.to_string(),
+        })
+    }
+
+    // Positions whose NFT the wallet holds, remembered so their updates reach the wallet's subscribers
+    #[instrument(skip(self), fields(wallet = %wallet))]
+    async fn get_wallet_positions(&self, wallet: &str) -> Option<WalletPositionsMessage> {
+        let positions = match self.database.get_wallet_positions(wallet).await {
+            Ok(positions) => positions,
+            Err(e) => {
+                error!(wallet = %wallet, error = %e, "❌ Failed to read wallet positions from database");
+                return None;
+            }
+        };
+
+        let mut accounts = match self.database.get_latest_account_states(&positions).await {
+            Ok(accounts) => accounts,
+            Err(e) => {
+                error!(wallet = %wallet, error = %e, "❌ Failed to read wallet position states from database");
+                return None;
+            }
+        };
+        accounts.retain(|account| !account.is_tombstone());
+        accounts.sort_by(|a, b| a.pubkey.cmp(&b.pubkey));
+
+        {
+            let mut holdings = self.wallet_holdings.write().await;
+            for account in &accounts {
+                holdings.insert(account.pubkey.clone(), wallet.to_string());
+            }
+        }
+
+        Some(WalletPositionsMessage {
+            wallet: wallet.to_string(),
+            positions: accounts.iter().map(AccountUpdate::without_raw_data).collect(),
+        })
+    }
+
     #[instrument(skip(self), fields(client_id))]
     async fn cleanup_client(&self, client_id: ClientId) {
         info!(client_id, "🧹 Starting client cleanup process");
@@ -353,6 +885,114 @@ impl WebSocketServer {
             }
         }
 
+        self.encodings.write().await.retain(|(id, _), _| *id != client_id);
+
+        // Remove client from all trade, block, new pool, pool position and wallet subscriptions
+        for subscriptions in [
+            &self.trade_subscriptions,
+            &self.block_subscriptions,
+            &self.new_pool_subscriptions,
+            &self.pool_position_subscriptions,
+            &self.wallet_subscriptions,
+        ] {
+            let mut subs = subscriptions.write().await;
+            for (_key, client_list) in subs.iter_mut() {
+                client_list.retain(|&id| id != client_id);
+            }
+            subs.retain(|_, client_list| !client_list.is_empty());
+        }
+        self.prune_wallet_holdings().await;
+
         info!(client_id, "✅ Client cleanup completed successfully");
     }
 }
+
+// Raw data is only attached for subscriptions that asked for an encoding
+// Sends the message to each client, logging those that have disconnected
+fn send_to_clients<'a>(
+    clients: &HashMap<ClientId, broadcast::Sender<ServerMessage>>,
+    client_ids: impl IntoIterator<Item = &'a ClientId>,
+    message: &ServerMessage,
+) {
+    for &client_id in client_ids {
+        match clients.get(&client_id) {
+            Some(tx) => {
+                if tx.send(message.clone()).is_err() {
+                    // Client's receiver is dropped (client disconnected)
+                    warn!(client_id, "⚠️ Client appears to be disconnected during broadcast");
+                }
+            }
+            None => warn!(client_id, "⚠️ Client not found in clients map during broadcast"),
+        }
+    }
+}
+
+fn account_update_message(
+    pubkey: &str,
+    account: &AccountUpdate,
+    source: String,
+    encoding: Option<DataEncoding>,
+) -> AccountUpdateMessage {
+    let data = encoding.and_then(|encoding| {
+        let data_base64 = account.data_base64.as_ref()?;
+        match encoding {
+            DataEncoding::Base64 => Some([data_base64.clone(), "base64".to_string()]),
+            DataEncoding::Base64Zstd => {
+                let raw = BASE64_STANDARD.decode(data_base64).ok()?;
+                let compressed = zstd::encode_all(raw.as_slice(), 0).ok()?;
+                Some([BASE64_STANDARD.encode(compressed), "base64+zstd".to_string()])
+            }
+        }
+    });
+
+    AccountUpdateMessage {
+        pubkey: pubkey.to_string(),
+        account: AccountUpdate {
+            derived: None,
+            mints: None,
+            ..account.without_raw_data()
+        },
+        source,
+        data,
+        derived: account.derived,
+        mints: account.mints.clone(),
+    }
+}
+
+fn closed_message(pubkey: &str, account: &AccountUpdate, source: String) -> ClosedMessage {
+    ClosedMessage {
+        pubkey: pubkey.to_string(),
+        slot: account.slot,
+        reason: account.event_type.clone(),
+        owner: account.owner.clone(),
+        account: account.without_raw_data(),
+        source,
+    }
+}
+
+async fn add_subscriber(
+    subscriptions: &RwLock<HashMap<String, Vec<ClientId>>>,
+    key: &str,
+    client_id: ClientId,
+) {
+    let mut subs = subscriptions.write().await;
+    subs.entry(key.to_string())
+        .or_insert_with(Vec::new)
+        .push(client_id);
+}
+
+async fn remove_subscriber(
+    subscriptions: &RwLock<HashMap<String, Vec<ClientId>>>,
+    key: &str,
+    client_id: ClientId,
+) {
+    let mut subs = subscriptions.write().await;
+    if let Some(client_list) = subs.get_mut(key) {
+        client_list.retain(|&id| id != client_id);
+
+        // Clean up empty subscription lists
+        if client_list.is_empty() {
+            subs.remove(key);
+        }
+    }
+}
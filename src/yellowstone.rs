@@ -0,0 +1,452 @@
+use async_trait::async_trait;
+use carbon_core::datasource::{AccountUpdate, Datasource, DatasourceId, Update, UpdateType};
+use carbon_core::error::{CarbonResult, Error as CarbonError};
+use carbon_core::metrics::MetricsCollection;
+use futures_util::{SinkExt, StreamExt};
+use solana_pubkey::Pubkey;
+use solana_signature::Signature;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::Sender;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn, error, debug, instrument};
+use yellowstone_grpc_client::{ClientTlsConfig, GeyserGrpcClient};
+use yellowstone_grpc_proto::prelude::{
+    CommitmentLevel, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterAccountsFilter,
+    SubscribeRequestFilterAccountsFilterMemcmp, SubscribeRequestPing, SubscribeUpdateAccountInfo,
+    subscribe_request_filter_accounts_filter::Filter as AccountsFilter,
+    subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpData, subscribe_update::UpdateOneof,
+};
+
+use crate::config::YellowstoneConfig;
+use crate::filters::FilterSet;
+
+// Write versions are kept for this many slots in case the account is never processed
+const WRITE_VERSION_RETENTION_SLOTS: u64 = 150;
+
+// Geyser write versions, handed from the datasource to the processor since Carbon updates don't carry them
+#[derive(Debug, Default)]
+pub struct WriteVersions {
+    versions: Mutex<HashMap<(Pubkey, u64), u64>>,
+}
+
+impl WriteVersions {
+    fn record(&self, pubkey: Pubkey, slot: u64, write_version: u64) {
+        let mut versions = self.versions.lock().unwrap();
+        versions.insert((pubkey, slot), write_version);
+
+        // Drop entries for accounts no decoder picked up
+        if versions.len() > 10_000 {
+            versions.retain(|(_, recorded_slot), _| {
+                *recorded_slot + WRITE_VERSION_RETENTION_SLOTS >= slot
+            });
+        }
+    }
+
+    pub fn take(&self, pubkey: &Pubkey, slot: u64) -> Option<u64> {
+        self.versions.lock().unwrap().remove(&(*pubkey, slot))
+    }
+}
+
+// Account stream from a Yellowstone gRPC (Geyser) endpoint, filtered by owner, account data and pubkey
+pub struct YellowstoneGrpcDatasource {
+    config: YellowstoneConfig,
+    // Each owner with the filter sets its accounts must match one of
+    owners: Vec<(Pubkey, Vec<FilterSet>)>,
+    commitment: CommitmentLevel,
+    write_versions: Arc<WriteVersions>,
+}
+
+impl YellowstoneGrpcDatasource {
+    pub fn new(
+        config: YellowstoneConfig,
+        owners: Vec<(Pubkey, Vec<FilterSet>)>,
+        commitment: CommitmentLevel,
+        write_versions: Arc<WriteVersions>,
+    ) -> Self {
+        Self {
+            config,
+            owners,
+            commitment,
+            write_versions,
+        }
+    }
+
+    fn subscribe_request(&self) -> SubscribeRequest {
+        let mut accounts = HashMap::new();
+        // Named filters are ORed by Geyser, while the filters inside one must all match
+        for (owner, filter_sets) in &self.owners {
+            for (index, filter_set) in filter_sets.iter().enumerate() {
+                accounts.insert(
+                    format!("{}:{}", owner, index),
+                    SubscribeRequestFilterAccounts {
+                        owner: vec![owner.to_string()],
+                        filters: geyser_filters(filter_set),
+                        ..Default::default()
+                    },
+                );
+            }
+        }
+        if !self.config.accounts.is_empty() {
+            accounts.insert(
+                "accounts".to_string(),
+                SubscribeRequestFilterAccounts {
+                    account: self.config.accounts.clone(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        SubscribeRequest {
+            accounts,
+            commitment: Some(self.commitment as i32),
+            ..Default::default()
+        }
+    }
+
+    fn to_update(&self, info: SubscribeUpdateAccountInfo, slot: u64) -> Option<Update> {
+        let pubkey = Pubkey::try_from(info.pubkey.as_slice()).ok()?;
+        let owner = Pubkey::try_from(info.owner.as_slice()).ok()?;
+        let transaction_signature = info
+            .txn_signature
+            .as_deref()
+            .and_then(|signature| Signature::try_from(signature).ok());
+
+        self.write_versions.record(pubkey, slot, info.write_version);
+
+        Some(Update::Account(AccountUpdate {
+            pubkey,
+            account: solana_account::Account {
+                lamports: info.lamports,
+                data: info.data,
+                owner,
+                executable: info.executable,
+                rent_epoch: info.rent_epoch,
+            },
+            slot,
+            transaction_signature,
+        }))
+    }
+}
+
+#[async_trait]
+impl Datasource for YellowstoneGrpcDatasource {
+    #[instrument(skip_all, fields(endpoint = %self.config.endpoint))]
+    async fn consume(
+        &self,
+        id: DatasourceId,
+        sender: Sender<(Update, DatasourceId)>,
+        cancellation_token: CancellationToken,
+        _metrics: Arc<MetricsCollection>,
+    ) -> CarbonResult<()> {
+        let mut builder = GeyserGrpcClient::build_from_shared(self.config.endpoint.clone())
+            .and_then(|builder| builder.x_token(self.config.x_token.clone()))
+            .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+        // Plaintext endpoints (a local node, or a sidecar terminating TLS) are dialled as they are
+        if self.config.endpoint.starts_with("https://") {
+            builder = builder
+                .tls_config(ClientTlsConfig::new().with_native_roots())
+                .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+        }
+        let mut client = builder
+            .connect_timeout(Duration::from_secs(10))
+            .timeout(Duration::from_secs(10))
+            .connect()
+            .await
+            .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+
+        let (mut subscribe_tx, mut stream) = client
+            .subscribe_with_request(Some(self.subscribe_request()))
+            .await
+            .map_err(|e| CarbonError::FailedToConsumeDatasource(e.to_string()))?;
+        info!(endpoint = %self.config.endpoint, commitment = ?self.commitment, "🛰️ Subscribed to Yellowstone gRPC account stream");
+
+        loop {
+            let message = tokio::select! {
+                _ = cancellation_token.cancelled() => {
+                    info!("🛑 Yellowstone gRPC datasource cancelled");
+                    return Ok(());
+                }
+                message = stream.next() => message,
+            };
+
+            let update = match message {
+                Some(Ok(update)) => update,
+                Some(Err(status)) => {
+                    error!(error = %status, "❌ Yellowstone gRPC stream error");
+                    return Err(CarbonError::FailedToConsumeDatasource(status.to_string()));
+                }
+                None => {
+                    warn!("⚠️ Yellowstone gRPC stream closed by server");
+                    return Err(CarbonError::FailedToConsumeDatasource(
+                        "Yellowstone gRPC stream closed".to_string(),
+                    ));
+                }
+            };
+
+            match update.update_oneof {
+                Some(UpdateOneof::Account(account_update)) => {
+                    let Some(info) = account_update.account else {
+                        continue;
+                    };
+                    if let Some(update) = self.to_update(info, account_update.slot) {
+                        if sender.send((update, id.clone())).await.is_err() {
+                            debug!("📪 Pipeline receiver dropped, stopping Yellowstone gRPC datasource");
+                            return Ok(());
+                        }
+                    }
+                }
+                // Answer server pings so load balancers keep the stream open
+                Some(UpdateOneof::Ping(_)) => {
+                    let ping = SubscribeRequest {
+                        ping: Some(SubscribeRequestPing { id: 1 }),
+                        ..Default::default()
+                    };
+                    if let Err(e) = subscribe_tx.send(ping).await {
+                        warn!(error = %e, "⚠️ Failed to answer Yellowstone gRPC ping");
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn update_types(&self) -> Vec<UpdateType> {
+        vec![UpdateType::AccountUpdate]
+    }
+}
+
+fn geyser_filters(filter_set: &FilterSet) -> Vec<SubscribeRequestFilterAccountsFilter> {
+    let data_size = filter_set.data_size.map(AccountsFilter::Datasize);
+    let memcmp = filter_set.memcmp.iter().map(|memcmp| {
+        AccountsFilter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+            offset: memcmp.offset as u64,
+            data: Some(MemcmpData::Bytes(memcmp.bytes.clone())),
+        })
+    });
+
+    data_size
+        .into_iter()
+        .chain(memcmp)
+        .map(|filter| SubscribeRequestFilterAccountsFilter { filter: Some(filter) })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filters::MemcmpFilter;
+    use std::pin::Pin;
+    use tokio::net::TcpListener;
+    use tokio_stream::Stream;
+    use tokio_stream::wrappers::{ReceiverStream, TcpListenerStream};
+    use yellowstone_grpc_proto::geyser::geyser_server::{Geyser, GeyserServer};
+    use yellowstone_grpc_proto::prelude::{
+        GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest, GetLatestBlockhashResponse,
+        GetSlotRequest, GetSlotResponse, GetVersionRequest, GetVersionResponse, IsBlockhashValidRequest,
+        IsBlockhashValidResponse, PingRequest, PongResponse, SubscribeReplayInfoRequest,
+        SubscribeReplayInfoResponse, SubscribeUpdate, SubscribeUpdateAccount, SubscribeUpdatePing,
+    };
+    use yellowstone_grpc_proto::tonic::{self, Request, Response, Status, Streaming, transport::Server};
+
+    type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+    // Geyser server that answers every subscription with a fixed list of updates, then hangs up
+    struct MockGeyser {
+        updates: Vec<SubscribeUpdate>,
+        requests: tokio::sync::mpsc::UnboundedSender<SubscribeRequest>,
+    }
+
+    #[tonic::async_trait]
+    impl Geyser for MockGeyser {
+        type SubscribeStream = UpdateStream;
+
+        async fn subscribe(
+            &self,
+            request: Request<Streaming<SubscribeRequest>>,
+        ) -> Result<Response<Self::SubscribeStream>, Status> {
+            let mut inbound = request.into_inner();
+            let first = inbound.message().await?.ok_or_else(|| Status::invalid_argument("no subscribe request"))?;
+            let _ = self.requests.send(first);
+
+            let (tx, rx) = tokio::sync::mpsc::channel(16);
+            for update in self.updates.clone() {
+                tx.send(Ok(update)).await.unwrap();
+            }
+            Ok(Response::new(Box::pin(ReceiverStream::new(rx))))
+        }
+
+        async fn subscribe_replay_info(
+            &self,
+            _request: Request<SubscribeReplayInfoRequest>,
+        ) -> Result<Response<SubscribeReplayInfoResponse>, Status> {
+            Err(Status::unimplemented("subscribe_replay_info"))
+        }
+
+        async fn ping(&self, _request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+            Err(Status::unimplemented("ping"))
+        }
+
+        async fn get_latest_blockhash(
+            &self,
+            _request: Request<GetLatestBlockhashRequest>,
+        ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+            Err(Status::unimplemented("get_latest_blockhash"))
+        }
+
+        async fn get_block_height(
+            &self,
+            _request: Request<GetBlockHeightRequest>,
+        ) -> Result<Response<GetBlockHeightResponse>, Status> {
+            Err(Status::unimplemented("get_block_height"))
+        }
+
+        async fn get_slot(&self, _request: Request<GetSlotRequest>) -> Result<Response<GetSlotResponse>, Status> {
+            Err(Status::unimplemented("get_slot"))
+        }
+
+        async fn is_blockhash_valid(
+            &self,
+            _request: Request<IsBlockhashValidRequest>,
+        ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+            Err(Status::unimplemented("is_blockhash_valid"))
+        }
+
+        async fn get_version(&self, _request: Request<GetVersionRequest>) -> Result<Response<GetVersionResponse>, Status> {
+            Err(Status::unimplemented("get_version"))
+        }
+    }
+
+    // Serve the mock on a free local port, returning its plaintext endpoint
+    async fn serve(updates: Vec<SubscribeUpdate>) -> (String, tokio::sync::mpsc::UnboundedReceiver<SubscribeRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let (requests, received) = tokio::sync::mpsc::unbounded_channel();
+
+        tokio::spawn(
+            Server::builder()
+                .add_service(GeyserServer::new(MockGeyser { updates, requests }))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        (endpoint, received)
+    }
+
+    fn datasource(endpoint: String, owner: Pubkey, write_versions: Arc<WriteVersions>) -> YellowstoneGrpcDatasource {
+        YellowstoneGrpcDatasource::new(
+            YellowstoneConfig {
+                endpoint,
+                x_token: None,
+                accounts: vec![],
+            },
+            vec![(
+                owner,
+                vec![FilterSet {
+                    data_size: Some(8),
+                    memcmp: vec![MemcmpFilter {
+                        offset: 0,
+                        bytes: vec![1, 2],
+                    }],
+                }],
+            )],
+            CommitmentLevel::Confirmed,
+            write_versions,
+        )
+    }
+
+    fn account_update(pubkey: Pubkey, owner: Pubkey, slot: u64, write_version: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: pubkey.to_bytes().to_vec(),
+                    lamports: 42,
+                    owner: owner.to_bytes().to_vec(),
+                    data: vec![1, 2, 3, 4, 5, 6, 7, 8],
+                    write_version,
+                    ..Default::default()
+                }),
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn subscribe_request_carries_owner_filters_and_extra_accounts() {
+        let owner = Pubkey::new_unique();
+        let mut source = datasource("http://localhost:10000".to_string(), owner, Arc::default());
+        source.config.accounts = vec![Pubkey::new_unique().to_string()];
+
+        let request = source.subscribe_request();
+        assert_eq!(request.commitment, Some(CommitmentLevel::Confirmed as i32));
+        assert_eq!(request.accounts.len(), 2);
+
+        let owned = &request.accounts[&format!("{}:0", owner)];
+        assert_eq!(owned.owner, vec![owner.to_string()]);
+        assert_eq!(owned.filters.len(), 2);
+        assert_eq!(request.accounts["accounts"].account, source.config.accounts);
+    }
+
+    #[tokio::test]
+    async fn streams_account_updates_from_a_plaintext_endpoint() {
+        let owner = Pubkey::new_unique();
+        let pubkey = Pubkey::new_unique();
+        let ping = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+            ..Default::default()
+        };
+        let (endpoint, mut requests) = serve(vec![account_update(pubkey, owner, 100, 7), ping]).await;
+
+        let write_versions = Arc::new(WriteVersions::default());
+        let source = datasource(endpoint, owner, write_versions.clone());
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(16);
+        let cancellation_token = CancellationToken::new();
+        let consume = tokio::spawn({
+            let cancellation_token = cancellation_token.clone();
+            async move {
+                source
+                    .consume(DatasourceId::new_named("yellowstone"), sender, cancellation_token, Arc::new(MetricsCollection::new(vec![])))
+                    .await
+            }
+        });
+
+        let request = requests.recv().await.unwrap();
+        assert!(request.accounts.contains_key(&format!("{}:0", owner)));
+
+        let (update, _) = tokio::time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap();
+        let Update::Account(update) = update else {
+            panic!("expected an account update");
+        };
+        assert_eq!(update.pubkey, pubkey);
+        assert_eq!(update.slot, 100);
+        assert_eq!(update.account.owner, owner);
+        assert_eq!(update.account.lamports, 42);
+        assert_eq!(write_versions.take(&pubkey, 100), Some(7));
+
+        cancellation_token.cancel();
+        let _ = consume.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_server_closes_the_stream() {
+        let owner = Pubkey::new_unique();
+        let (endpoint, _requests) = serve(vec![]).await;
+
+        let source = datasource(endpoint, owner, Arc::default());
+        let (sender, _receiver) = tokio::sync::mpsc::channel(16);
+        let result = tokio::time::timeout(
+            Duration::from_secs(5),
+            source.consume(
+                DatasourceId::new_named("yellowstone"),
+                sender,
+                CancellationToken::new(),
+                Arc::new(MetricsCollection::new(vec![])),
+            ),
+        )
+        .await
+        .unwrap();
+
+        assert!(matches!(result, Err(CarbonError::FailedToConsumeDatasource(_))));
+    }
+}